@@ -0,0 +1,325 @@
+use crate::stats::{DelayStats, Ewma, Score};
+use crate::summary::{MaximalsStampsBuffer, Position, SummaryOptions};
+use crate::timer::{Stamp, Timer};
+use regex::Regex;
+use std::time::Duration;
+
+/// which line a delay between two lines is attributed to
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Attribution {
+    /// the line arriving after the delay
+    #[default]
+    After,
+    /// the line preceding the delay, its prepended time is printed after it
+    Before,
+}
+
+/// Settings of an [`Analyzer`]
+#[derive(Clone)]
+pub struct AnalyzerOptions {
+    /// number of maximals kept
+    pub count: usize,
+    /// lines kept before and after each maximal
+    pub lines_before: usize,
+    pub lines_after: usize,
+    pub summary: SummaryOptions,
+    pub attribute: Attribution,
+    /// lines starting a record, the others are continuations; every line starts one if unset
+    pub record_start: Option<Regex>,
+    /// only lines matching one of these are timed, all of them if empty
+    pub only: Vec<Regex>,
+    /// lines matching one of these are not timed
+    pub ignore: Vec<Regex>,
+    /// smoothing factor when ranking delays by anomaly instead of length
+    pub anomaly_alpha: Option<f64>,
+    /// delays from which statistics count slow lines, when collecting them
+    pub stats: Option<Duration>,
+}
+
+impl Default for AnalyzerOptions {
+    fn default() -> Self {
+        AnalyzerOptions {
+            count: 5,
+            lines_before: 0,
+            lines_after: 0,
+            summary: SummaryOptions::default(),
+            attribute: Attribution::After,
+            record_start: None,
+            only: vec![],
+            ignore: vec![],
+            anomaly_alpha: None,
+            stats: None,
+        }
+    }
+}
+
+/// a timed line together with its continuation lines
+pub struct Record {
+    pub stamp: Stamp,
+    /// anomaly of the delay, once it is known
+    pub score: Score,
+    pub position: Position,
+    pub text: String,
+}
+
+/// time to print next to a line, once its delay is known
+#[derive(Clone, Copy)]
+pub struct Annotation {
+    pub stamp: Stamp,
+    pub score: Score,
+    pub position: Position,
+}
+
+/// what an [`Analyzer`] learned from a line
+#[derive(Default)]
+pub struct Step {
+    /// stamp of the line, if it was timed
+    pub stamp: Option<Stamp>,
+    /// anomaly of the delay of the line, if already known
+    pub score: Score,
+    pub annotation: Option<Annotation>,
+    /// records now complete with their delay, in input order
+    pub records: Vec<Record>,
+}
+
+/// Times lines and keeps the largest delays, with optional statistics
+pub struct Analyzer {
+    timer: Box<dyn Timer>,
+    options: AnalyzerOptions,
+    max: MaximalsStampsBuffer,
+    stats: Option<DelayStats>,
+    anomaly: Option<Ewma>,
+    position: Position,
+    /// record still collecting continuation lines
+    record: Option<Record>,
+    /// last record waiting for the following delay when attributing to the line before
+    pending: Option<Record>,
+}
+
+impl Analyzer {
+    pub fn new(timer: Box<dyn Timer>, options: AnalyzerOptions) -> Self {
+        let (max, stats) = Self::summaries(&options);
+        Analyzer {
+            timer,
+            anomaly: options.anomaly_alpha.map(Ewma::new),
+            options,
+            max,
+            stats,
+            position: Position::default(),
+            record: None,
+            pending: None,
+        }
+    }
+
+    /// empty maximals and statistics
+    fn summaries(options: &AnalyzerOptions) -> (MaximalsStampsBuffer, Option<DelayStats>) {
+        let max = MaximalsStampsBuffer::new(
+            options.count,
+            options.lines_before,
+            options.lines_after,
+            options.summary,
+        );
+        (max, options.stats.map(DelayStats::new))
+    }
+
+    /// time the following lines with `timer`
+    pub fn set_timer(&mut self, timer: Box<dyn Timer>) {
+        self.timer = timer;
+    }
+
+    /// forget the maximals and statistics collected so far
+    pub fn reset(&mut self) {
+        (self.max, self.stats) = Self::summaries(&self.options);
+    }
+
+    pub fn maximals(&self) -> &MaximalsStampsBuffer {
+        &self.max
+    }
+
+    pub fn stats(&self) -> Option<&DelayStats> {
+        self.stats.as_ref()
+    }
+
+    /// analyze a line, with its line ending if any
+    pub fn insert(&mut self, line: &str) -> Step {
+        self.insert_decoded(line, line.len())
+    }
+
+    /// analyze a line decoded from `len` bytes of input, lossily when they are not UTF-8, so
+    /// that byte offsets stay those of the input
+    pub fn insert_decoded(&mut self, line: &str, len: usize) -> Step {
+        self.position.line += 1;
+        let position = self.position;
+        self.position.offset += len;
+
+        let start = self
+            .options
+            .record_start
+            .as_ref()
+            .is_none_or(|r| r.is_match(line));
+
+        let mut step = Step {
+            stamp: (start && self.timed(line))
+                .then(|| self.timer.stamp(line))
+                .flatten(),
+            ..Step::default()
+        };
+        match step.stamp {
+            Some(stamp) => {
+                self.finish_record(&mut step.records);
+                self.annotate(&stamp, position, &mut step);
+                self.record = Some(Record {
+                    stamp,
+                    score: step.score,
+                    position,
+                    text: line.to_string(),
+                });
+                if self.options.record_start.is_none() {
+                    self.finish_record(&mut step.records);
+                }
+            }
+            None => {
+                if let Some(record) = &mut self.record {
+                    record.text.push_str(line);
+                }
+            }
+        }
+        step
+    }
+
+    fn timed(&self, line: &str) -> bool {
+        let line = line.trim_end_matches(['\r', '\n']);
+        (self.options.only.is_empty() || self.options.only.iter().any(|r| r.is_match(line)))
+            && !self.options.ignore.iter().any(|r| r.is_match(line))
+    }
+
+    /// anomaly of the next delay, when ranking by anomaly
+    fn score(&mut self, last: Duration) -> Score {
+        self.anomaly
+            .as_mut()
+            .map_or(Score::default(), |a| a.insert(last))
+    }
+
+    /// attribute the delay `stamp.last` to its line
+    fn annotate(&mut self, stamp: &Stamp, position: Position, step: &mut Step) {
+        match self.options.attribute {
+            Attribution::After => {
+                step.score = self.score(stamp.last);
+                step.annotation = Some(Annotation {
+                    stamp: *stamp,
+                    score: step.score,
+                    position,
+                });
+            }
+            Attribution::Before => {
+                if self.pending.is_some() {
+                    let score = self.score(stamp.last);
+                    if let Some(pending) = &mut self.pending {
+                        pending.stamp.last = stamp.last;
+                        pending.score = score;
+                        step.annotation = Some(Annotation {
+                            stamp: pending.stamp,
+                            score,
+                            position: pending.position,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// the current record is complete, analyze it once its delay is known
+    fn finish_record(&mut self, records: &mut Vec<Record>) {
+        if let Some(record) = self.record.take() {
+            match self.options.attribute {
+                Attribution::After => records.push(self.commit(record)),
+                Attribution::Before => {
+                    if let Some(pending) = self.pending.replace(record) {
+                        records.push(self.commit(pending));
+                    }
+                }
+            }
+        }
+    }
+
+    fn commit(&mut self, record: Record) -> Record {
+        if let Some(stats) = &mut self.stats {
+            stats.insert(record.stamp.last, record.stamp.total);
+        }
+        self.max
+            .insert(record.stamp, record.score, record.position, &record.text);
+        record
+    }
+
+    /// the input ended, analyze the records still waiting for continuations or delays
+    pub fn finish(&mut self) -> Step {
+        let mut step = Step::default();
+        self.finish_record(&mut step.records);
+        if let Some(pending) = self.pending.take() {
+            // nothing follows the last line, there is no delay to attribute to it
+            let stamp = Stamp {
+                last: Duration::ZERO,
+                ..pending.stamp
+            };
+            let score = self.score(stamp.last);
+            step.annotation = Some(Annotation {
+                stamp,
+                score,
+                position: pending.position,
+            });
+            step.records.push(self.commit(Record {
+                stamp,
+                score,
+                ..pending
+            }));
+        }
+        step
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analyzer::{Analyzer, AnalyzerOptions, Attribution};
+    use crate::timer::RegexTimer;
+    use regex::Regex;
+
+    fn analyzer(attribute: Attribution) -> Analyzer {
+        let regex = Regex::new(r"^(?P<time>\d+)").unwrap();
+        let options = AnalyzerOptions {
+            count: 2,
+            attribute,
+            record_start: Some(Regex::new(r"^\d").unwrap()),
+            ..AnalyzerOptions::default()
+        };
+        Analyzer::new(Box::new(RegexTimer::new(regex, "%s")), options)
+    }
+
+    #[test]
+    fn records() {
+        let mut a = analyzer(Attribution::After);
+        assert_eq!(a.insert("10 a\n").records.len(), 0);
+        assert!(a.insert("  more\n").stamp.is_none());
+        let step = a.insert("13 b\n");
+        assert_eq!(step.stamp.unwrap().last.as_secs(), 3);
+        assert_eq!(step.records[0].text, "10 a\n  more\n");
+        a.insert("14 c\n");
+        assert_eq!(a.finish().records.len(), 1);
+
+        let lines = a.maximals().entries().map(|e| e.line()).collect::<Vec<_>>();
+        assert_eq!(lines, ["13 b\n", "14 c\n"]);
+    }
+
+    #[test]
+    fn before() {
+        let mut a = analyzer(Attribution::Before);
+        assert!(a.insert("10 a\n").annotation.is_none());
+        let annotation = a.insert("13 b\n").annotation.unwrap();
+        assert_eq!(annotation.position.line, 1);
+        assert_eq!(annotation.stamp.last.as_secs(), 3);
+        assert_eq!(a.finish().annotation.unwrap().position.line, 2);
+
+        let lines = a.maximals().entries().map(|e| e.line()).collect::<Vec<_>>();
+        assert_eq!(lines, ["10 a\n", "13 b\n"]);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::templates::Templates;
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Formatter;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Template totals of a known-good run, stored as json lines of `[total, count, template]`
+pub struct Baseline {
+    totals: HashMap<String, Duration>,
+}
+
+impl Baseline {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut totals = HashMap::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (total, _count, template): (f64, usize, String) = serde_json::from_str(&line)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let total = Duration::try_from_secs_f64(total)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            totals.insert(template, total);
+        }
+        Ok(Baseline { totals })
+    }
+
+    pub fn save(templates: &Templates, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        for (template, total, count) in templates.iter().sorted_by_key(|(t, ..)| *t) {
+            let line = serde_json::json!([total.as_secs_f64(), count, template]);
+            writeln!(writer, "{line}")?;
+        }
+        writer.flush()
+    }
+
+    /// templates of the run exceeding their baseline total by more than `threshold` percent
+    pub fn regressions(&self, templates: &Templates, threshold: f32, count: usize) -> Regressions {
+        let entries = templates
+            .iter()
+            .filter_map(|(template, total, _)| {
+                let baseline = *self.totals.get(template)?;
+                (total.as_secs_f32() > baseline.as_secs_f32() * (1.0 + threshold / 100.0))
+                    .then(|| (template.to_string(), baseline, total))
+            })
+            .sorted_by(|(a_name, a_base, a), (b_name, b_base, b)| {
+                (*b - *b_base).cmp(&(*a - *a_base)).then(a_name.cmp(b_name))
+            })
+            .collect();
+        Regressions {
+            entries,
+            threshold,
+            compared: templates
+                .iter()
+                .filter(|(t, ..)| self.totals.contains_key(*t))
+                .count(),
+            count,
+        }
+    }
+}
+
+/// Templates slower than in the baseline, by decreasing excess time
+pub struct Regressions {
+    entries: Vec<(String, Duration, Duration)>,
+    threshold: f32,
+    compared: usize,
+    count: usize,
+}
+
+impl Regressions {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl fmt::Display for Regressions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "over +{}%: {} of {} templates",
+            self.threshold,
+            self.entries.len(),
+            self.compared
+        )?;
+        for (template, baseline, total) in self.entries.iter().take(self.count) {
+            writeln!(
+                f,
+                "{:>12.4} {:>12.4} {:>+8.1}% {template}",
+                baseline.as_secs_f32(),
+                total.as_secs_f32(),
+                (total.as_secs_f32() / baseline.as_secs_f32() - 1.0) * 100.0
+            )?;
+        }
+        if self.entries.len() > self.count {
+            writeln!(f, "... and {} more", self.entries.len() - self.count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::baseline::Baseline;
+    use crate::templates::Templates;
+    use std::time::Duration;
+
+    #[test]
+    fn regressions() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("baseline.jsonl");
+
+        let mut good = Templates::new(1);
+        good.insert("compile 1", Duration::from_millis(100));
+        good.insert("link", Duration::from_millis(50));
+        good.insert("test", Duration::from_millis(40));
+        Baseline::save(&good, &path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "[0.1,1,\"compile <num>\"]\n[0.05,1,\"link\"]\n[0.04,1,\"test\"]\n"
+        );
+
+        let mut run = Templates::new(1);
+        run.insert("compile 2", Duration::from_millis(200));
+        run.insert("link", Duration::from_millis(55));
+        run.insert("test", Duration::from_millis(60));
+        run.insert("new", Duration::from_millis(500));
+
+        let regressions = Baseline::load(&path).unwrap().regressions(&run, 20.0, 1);
+        assert!(!regressions.is_empty());
+        assert_eq!(
+            regressions.to_string(),
+            "over +20%: 2 of 3 templates\n      \
+                   0.1000       0.2000   +100.0% compile <num>\n\
+             ... and 1 more\n"
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use crate::color::delay_rgb;
+use crate::Handler;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use regex::Regex;
+use std::io;
+use std::io::IsTerminal;
+use std::time::Duration;
+use txt_timer::summary::MaximalsStampsEntry;
+
+const HELP: &str =
+    " ↑↓ select  1-9 nth slowest  +/- context  PgUp/PgDn scroll  / search  n/N next/prev  q quit";
+
+/// Interactive pager over the whole input, moving between the ranked maximals
+struct Browser<'a> {
+    entries: Vec<&'a MaximalsStampsEntry>,
+    lines: &'a [(Option<Duration>, String)],
+    color_range: f32,
+    selected: ListState,
+    /// 0-based index of the line at the center of the view
+    focus: usize,
+    context: usize,
+    search: Option<Regex>,
+    /// search being typed
+    input: Option<String>,
+    message: String,
+}
+
+pub fn run(handler: &Handler) -> io::Result<()> {
+    let mut browser = Browser {
+        entries: handler.analyzer.maximals().entries().collect(),
+        lines: &handler.history,
+        color_range: handler.cli.color_range,
+        selected: ListState::default(),
+        focus: 0,
+        context: handler.cli.lines_before.max(handler.cli.lines_after),
+        search: None,
+        input: None,
+        message: String::new(),
+    };
+    browser.select(0);
+
+    // the pager is drawn on standard output, it must not end up in a redirection
+    let terminal = match io::stdout().is_terminal() {
+        true => ratatui::try_init(),
+        false => Err(io::Error::other("standard output is not a terminal")),
+    };
+    let mut terminal = match terminal {
+        Ok(terminal) => terminal,
+        Err(e) => {
+            eprintln!("not browsing the maximals, --browse needs a terminal: {e}");
+            return Ok(());
+        }
+    };
+    let rv = browser.run(&mut terminal);
+    ratatui::restore();
+    rv
+}
+
+impl Browser<'_> {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                if self.input.is_some() {
+                    self.edit_search(key.code);
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        return Ok(())
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        let i = self.selected.selected().unwrap_or(0);
+                        self.select(i.saturating_sub(1));
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        let i = self.selected.selected().map_or(0, |i| i + 1);
+                        self.select(i);
+                    }
+                    KeyCode::Char(c @ '1'..='9') => {
+                        self.select(c as usize - '1' as usize);
+                    }
+                    KeyCode::Char('+') => self.context += 1,
+                    KeyCode::Char('-') => self.context = self.context.saturating_sub(1),
+                    KeyCode::PageUp => self.focus = self.focus.saturating_sub(self.page()),
+                    KeyCode::PageDown => {
+                        self.focus = (self.focus + self.page()).min(self.last_line());
+                    }
+                    KeyCode::Char('/') => self.input = Some(String::new()),
+                    KeyCode::Char('n') => self.find(true),
+                    KeyCode::Char('N') => self.find(false),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn page(&self) -> usize {
+        2 * self.context + 1
+    }
+
+    fn last_line(&self) -> usize {
+        self.lines.len().saturating_sub(1)
+    }
+
+    fn select(&mut self, i: usize) {
+        if let Some(e) = self.entries.get(i) {
+            self.selected.select(Some(i));
+            self.focus = e.position.line - 1;
+        }
+    }
+
+    fn edit_search(&mut self, code: KeyCode) {
+        let Some(input) = &mut self.input else {
+            return;
+        };
+        match code {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Esc => self.input = None,
+            KeyCode::Enter => {
+                let pattern = self.input.take().unwrap_or_default();
+                self.search = Regex::new(&pattern)
+                    .or_else(|_| Regex::new(&regex::escape(&pattern)))
+                    .ok();
+                self.find(true);
+            }
+            _ => {}
+        }
+    }
+
+    /// move the focus to the next line matching the search, wrapping around
+    fn find(&mut self, forward: bool) {
+        let Some(search) = &self.search else {
+            return;
+        };
+        let len = self.lines.len();
+        let found = (1..=len)
+            .map(|i| {
+                if forward {
+                    (self.focus + i) % len
+                } else {
+                    (self.focus + len - i) % len
+                }
+            })
+            .find(|&i| search.is_match(&self.lines[i].1));
+        self.message = match found {
+            Some(i) => {
+                self.focus = i;
+                format!("/{}", search.as_str())
+            }
+            None => format!("/{} not found", search.as_str()),
+        };
+    }
+
+    fn delay_span(&self, delay: Option<Duration>) -> Span<'static> {
+        match delay {
+            Some(d) => {
+                let (r, g, b) = delay_rgb(d.as_secs_f32(), self.color_range);
+                Span::styled("█", Style::new().fg(Color::Rgb(r, g, b)))
+            }
+            None => Span::raw(" "),
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [body, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [maximals, lines] =
+            Layout::horizontal([Constraint::Length(40), Constraint::Min(0)]).areas(body);
+
+        self.draw_maximals(frame, maximals);
+        self.draw_lines(frame, lines);
+
+        let text = match &self.input {
+            Some(input) => format!("/{input}"),
+            None if self.message.is_empty() => HELP.to_string(),
+            None => format!(" {}  {HELP}", self.message),
+        };
+        frame.render_widget(Paragraph::new(text).reversed(), status);
+    }
+
+    fn draw_maximals(&mut self, frame: &mut Frame, area: Rect) {
+        let items = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(rank, e)| {
+                ListItem::new(Line::from(vec![
+                    self.delay_span(Some(e.stamp.last)),
+                    Span::raw(format!(
+                        " #{} Δ{:.4} line {}",
+                        rank + 1,
+                        e.stamp.last.as_secs_f32(),
+                        e.position.line
+                    )),
+                ]))
+            })
+            .collect::<Vec<_>>();
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::bordered().title("maximals"))
+                .highlight_style(Style::new().reversed()),
+            area,
+            &mut self.selected,
+        );
+    }
+
+    fn draw_lines(&self, frame: &mut Frame, area: Rect) {
+        let height = area.height.saturating_sub(2) as usize;
+        let shown = self.page().min(height);
+        let first = self
+            .focus
+            .saturating_sub(shown / 2)
+            .min(self.lines.len().saturating_sub(shown));
+        let slow = self
+            .selected
+            .selected()
+            .and_then(|i| self.entries.get(i))
+            .map(|e| e.position.line - 1);
+
+        let lines = self
+            .lines
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(shown)
+            .map(|(i, (delay, text))| {
+                let style = if i == self.focus {
+                    Style::new().reversed()
+                } else if Some(i) == slow {
+                    Style::new().yellow()
+                } else {
+                    Style::new()
+                };
+                Line::from(vec![
+                    Span::styled(format!("{:>7} ", i + 1), Style::new().blue()),
+                    self.delay_span(*delay),
+                    Span::raw(" "),
+                    Span::styled(text.as_str(), style),
+                ])
+            })
+            .collect::<Vec<_>>();
+        let title = format!("line {} of {}", self.focus + 1, self.lines.len());
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(title)),
+            area,
+        );
+    }
+}
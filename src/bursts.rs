@@ -0,0 +1,185 @@
+use chrono::{DateTime, Utc};
+use std::cmp::Reverse;
+use std::fmt;
+use std::fmt::Formatter;
+use std::rc::Rc;
+use std::time::Duration;
+use txt_timer::maximals::Maximals;
+use txt_timer::timer::Stamp;
+
+/// interval of time with the number of lines in it, most lines first
+#[derive(Eq, PartialEq, Ord, PartialOrd)]
+struct Interval {
+    lines: usize,
+    start: Reverse<DateTime<Utc>>,
+}
+
+/// consecutive lines with near-zero delays, longest first
+#[derive(Eq, PartialEq, Ord, PartialOrd)]
+struct Run {
+    lines: usize,
+    begin: Reverse<Duration>,
+    end: Duration,
+    line: usize,
+    text: Rc<str>,
+}
+
+/// Finds floods of lines: the intervals with most lines and the longest runs of near-zero delays
+pub struct Bursts {
+    interval: Duration,
+    gap: Duration,
+    intervals: Maximals<Interval>,
+    runs: Maximals<Run>,
+    current_interval: Option<Interval>,
+    current_run: Option<Run>,
+}
+
+impl Bursts {
+    pub fn new(interval: Duration, gap: Duration, count: usize) -> Self {
+        Bursts {
+            interval,
+            gap,
+            intervals: Maximals::new(count),
+            runs: Maximals::new(count),
+            current_interval: None,
+            current_run: None,
+        }
+    }
+
+    fn interval_start(&self, utc: DateTime<Utc>) -> DateTime<Utc> {
+        let width = (self.interval.as_millis() as i64).max(1);
+        let millis = utc.timestamp_millis().div_euclid(width) * width;
+        DateTime::from_timestamp_millis(millis).unwrap_or(utc)
+    }
+
+    pub fn insert(&mut self, stamp: &Stamp, line: usize, text: &str) {
+        let start = self.interval_start(stamp.utc);
+        match &mut self.current_interval {
+            Some(i) if i.start.0 >= start => i.lines += 1,
+            current => {
+                if let Some(i) = current.replace(Interval {
+                    lines: 1,
+                    start: Reverse(start),
+                }) {
+                    self.intervals.insert(i);
+                }
+            }
+        }
+
+        match &mut self.current_run {
+            // times going backwards, as in replayed casts out of order, end the run
+            Some(r) if stamp.last < self.gap && stamp.total >= r.end => {
+                r.lines += 1;
+                r.end = stamp.total;
+            }
+            current => {
+                let run = current.replace(Run {
+                    lines: 1,
+                    begin: Reverse(stamp.total),
+                    end: stamp.total,
+                    line,
+                    text: Rc::from(text.trim_end()),
+                });
+                if let Some(r) = run.filter(|r| r.lines > 1) {
+                    self.runs.insert(r);
+                }
+            }
+        }
+    }
+
+    /// the input ended, rank the current interval and run
+    pub fn finish(&mut self) {
+        if let Some(i) = self.current_interval.take() {
+            self.intervals.insert(i);
+        }
+        if let Some(r) = self.current_run.take().filter(|r| r.lines > 1) {
+            self.runs.insert(r);
+        }
+    }
+}
+
+impl fmt::Display for Bursts {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let interval = self.interval.as_secs_f32();
+        writeln!(f, "most lines per {interval}s:")?;
+        for i in self.intervals.iter() {
+            writeln!(
+                f,
+                "{:>8} lines {} ({:.1}/s)",
+                i.lines,
+                i.start.0.format("%Y-%m-%d %H:%M:%S%.3f"),
+                i.lines as f32 / interval
+            )?;
+        }
+        writeln!(
+            f,
+            "longest runs of delays under {}s:",
+            self.gap.as_secs_f32()
+        )?;
+        for r in self.runs.iter() {
+            writeln!(
+                f,
+                "{:>8} lines in {:.4} @{:.4} line {}: {}",
+                r.lines,
+                r.end.saturating_sub(r.begin.0).as_secs_f32(),
+                r.begin.0.as_secs_f32(),
+                r.line,
+                r.text
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bursts::Bursts;
+    use chrono::DateTime;
+    use std::time::Duration;
+    use txt_timer::timer::Stamp;
+
+    #[test]
+    fn bursts() {
+        let mut b = Bursts::new(Duration::from_secs(1), Duration::from_millis(1), 1);
+        let mut total = Duration::ZERO;
+        let begin = DateTime::from_timestamp(1670833140, 0).unwrap();
+        for (i, ms) in [0, 200, 0, 0, 900, 0, 100, 0, 0, 0].into_iter().enumerate() {
+            let last = Duration::from_millis(ms);
+            total += last;
+            let stamp = Stamp {
+                last,
+                total,
+                utc: begin + total,
+            };
+            b.insert(&stamp, i + 1, &format!("line {}\n", i + 1));
+        }
+        b.finish();
+
+        assert_eq!(
+            b.to_string(),
+            "most lines per 1s:\n       \
+                    6 lines 2022-12-12 08:19:01.000 (6.0/s)\n\
+             longest runs of delays under 0.001s:\n       \
+                    4 lines in 0.0000 @1.2000 line 7: line 7\n"
+        );
+    }
+
+    #[test]
+    fn backwards() {
+        let mut b = Bursts::new(Duration::from_secs(1), Duration::from_millis(1), 1);
+        let begin = DateTime::from_timestamp(1670833140, 0).unwrap();
+        for (i, total) in [5, 5, 2, 2].into_iter().enumerate() {
+            let total = Duration::from_secs(total);
+            let stamp = Stamp {
+                last: Duration::ZERO,
+                total,
+                utc: begin + total,
+            };
+            b.insert(&stamp, i + 1, "a\n");
+        }
+        b.finish();
+        assert!(b
+            .to_string()
+            .ends_with("2 lines in 0.0000 @2.0000 line 3: a\n"));
+    }
+}
@@ -0,0 +1,252 @@
+use clap::ValueEnum;
+use colored::Color;
+use std::env;
+use std::str::FromStr;
+
+/// when to color the output
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum ColorChoice {
+    /// on terminals, unless NO_COLOR is set or forced by CLICOLOR_FORCE
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// whether to color output going to a terminal or not
+    pub fn enabled(self, terminal: bool) -> bool {
+        let forced = env::var("CLICOLOR_FORCE").is_ok_and(|v| !v.is_empty() && v != "0");
+        let disabled = env::var("NO_COLOR").is_ok_and(|v| !v.is_empty())
+            || env::var("CLICOLOR").is_ok_and(|v| v == "0");
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => forced || (terminal && !disabled),
+        }
+    }
+}
+
+/// run `f` writing colors or not, as for a destination other than standard output
+pub fn with_colors<R>(enabled: bool, f: impl FnOnce() -> R) -> R {
+    let previous = colored::control::SHOULD_COLORIZE.should_colorize();
+    colored::control::set_override(enabled);
+    let rv = f();
+    colored::control::set_override(previous);
+    rv
+}
+
+/// how delays are spread over the green to red ramp
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ColorScale {
+    /// proportionally to the delay
+    Linear,
+    /// proportionally to the logarithm of the delay from 1ms, telling apart small delays
+    Log,
+}
+
+/// colors supported by the terminal
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum ColorDepth {
+    /// truecolor when COLORTERM says so, 256 colors when TERM says so, else 16 colors
+    Auto,
+    Truecolor,
+    #[value(name = "256")]
+    Ansi256,
+    #[value(name = "16")]
+    Ansi16,
+}
+
+impl ColorDepth {
+    fn detect() -> Self {
+        if env::var("COLORTERM").is_ok_and(|c| c == "truecolor" || c == "24bit") {
+            ColorDepth::Truecolor
+        } else if env::var("TERM").is_ok_and(|t| t.contains("256color")) {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+}
+
+/// named color of the delays from `delay` seconds up to the next threshold
+#[derive(Clone)]
+pub struct Threshold {
+    delay: f32,
+    color: Color,
+}
+
+impl FromStr for Threshold {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (delay, color) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected SECONDS=COLOR, got `{s}`"))?;
+        Ok(Threshold {
+            delay: delay
+                .trim()
+                .parse()
+                .map_err(|e| format!("invalid delay `{delay}`: {e}"))?,
+            color: color
+                .trim()
+                .parse()
+                .map_err(|()| format!("unknown color `{color}`"))?,
+        })
+    }
+}
+
+/// color of a delay `x` on a green to red scale over `range`
+pub fn delay_rgb(x: f32, range: f32) -> (u8, u8, u8) {
+    let x_scale = x / range;
+    let r: u8 = (255.0 * (2.0 * x_scale)).clamp(0.0, 255.0) as u8;
+    let g: u8 = (255.0 * (2.0 - 2.0 * x_scale)).clamp(0.0, 255.0) as u8;
+    (r, g, 0)
+}
+
+/// basic colors with their rgb, to pick the closest on 16 color terminals
+const BASIC: [(Color, (u8, u8, u8)); 8] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (255, 0, 0)),
+    (Color::Green, (0, 255, 0)),
+    (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (0, 0, 255)),
+    (Color::Magenta, (255, 0, 255)),
+    (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// Colors of delays, on a green to red ramp or by thresholds
+pub struct Palette {
+    range: f32,
+    scale: ColorScale,
+    thresholds: Vec<Threshold>,
+    depth: ColorDepth,
+}
+
+impl Palette {
+    /// smallest delay told apart on the log scale
+    const LOG_MIN: f32 = 0.001;
+
+    pub fn new(
+        range: f32,
+        scale: ColorScale,
+        mut thresholds: Vec<Threshold>,
+        depth: ColorDepth,
+    ) -> Self {
+        thresholds.sort_by(|a, b| a.delay.total_cmp(&b.delay));
+        let depth = match depth {
+            ColorDepth::Auto => ColorDepth::detect(),
+            depth => depth,
+        };
+        Palette {
+            range,
+            scale,
+            thresholds,
+            depth,
+        }
+    }
+
+    /// color of a delay `x` on the green to red ramp, red from `range`
+    pub fn rgb(&self, x: f32) -> (u8, u8, u8) {
+        match self.scale {
+            ColorScale::Linear => delay_rgb(x, self.range),
+            ColorScale::Log => delay_rgb(
+                (x.max(Self::LOG_MIN) / Self::LOG_MIN).ln(),
+                (self.range.max(Self::LOG_MIN * 2.0) / Self::LOG_MIN).ln(),
+            ),
+        }
+    }
+
+    /// SGR parameters of the color of `x`, none below the first threshold
+    fn sgr(&self, x: f32, background: bool) -> Option<String> {
+        if !self.thresholds.is_empty() {
+            let t = self.thresholds.iter().rev().find(|t| x >= t.delay)?;
+            return Some(if background {
+                t.color.to_bg_str().into_owned()
+            } else {
+                t.color.to_fg_str().into_owned()
+            });
+        }
+
+        let (r, g, b) = self.rgb(x);
+        let layer = if background { 48 } else { 38 };
+        Some(match self.depth {
+            ColorDepth::Truecolor | ColorDepth::Auto => format!("{layer};2;{r};{g};{b}"),
+            ColorDepth::Ansi256 => {
+                let level = |c: u8| (c as u16 * 5 + 127) / 255;
+                let index = 16 + 36 * level(r) + 6 * level(g) + level(b);
+                format!("{layer};5;{index}")
+            }
+            ColorDepth::Ansi16 => {
+                let distance = |(r2, g2, b2): (u8, u8, u8)| {
+                    [(r, r2), (g, g2), (b, b2)]
+                        .map(|(a, b)| (a as i32 - b as i32).pow(2))
+                        .iter()
+                        .sum::<i32>()
+                };
+                let (color, _) = BASIC.iter().min_by_key(|(_, rgb)| distance(*rgb))?;
+                if background {
+                    color.to_bg_str().into_owned()
+                } else {
+                    color.to_fg_str().into_owned()
+                }
+            }
+        })
+    }
+
+    /// `text` in the color of delay `x`, or its background
+    pub fn paint(&self, text: &str, x: f32, background: bool) -> String {
+        match self.sgr(x, background) {
+            Some(sgr) if colored::control::SHOULD_COLORIZE.should_colorize() => {
+                format!("\x1b[{sgr}m{text}\x1b[0m")
+            }
+            _ => text.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::{ColorChoice, ColorDepth, ColorScale, Palette, Threshold};
+
+    #[test]
+    fn choice() {
+        assert!(ColorChoice::Always.enabled(false));
+        assert!(!ColorChoice::Never.enabled(true));
+    }
+
+    #[test]
+    fn scales() {
+        let linear = Palette::new(1.0, ColorScale::Linear, vec![], ColorDepth::Truecolor);
+        assert_eq!(linear.rgb(0.0), (0, 255, 0));
+        assert_eq!(linear.rgb(0.5), (255, 255, 0));
+        assert_eq!(linear.rgb(2.0), (255, 0, 0));
+
+        let log = Palette::new(1.0, ColorScale::Log, vec![], ColorDepth::Truecolor);
+        assert_eq!(log.rgb(0.0), (0, 255, 0));
+        assert_eq!(log.rgb(0.0316), (254, 255, 0));
+        assert_eq!(log.rgb(1.0), (255, 0, 0));
+    }
+
+    #[test]
+    fn depths() {
+        let sgr = |depth| Palette::new(1.0, ColorScale::Linear, vec![], depth).sgr(1.0, false);
+        assert_eq!(sgr(ColorDepth::Truecolor).unwrap(), "38;2;255;0;0");
+        assert_eq!(sgr(ColorDepth::Ansi256).unwrap(), "38;5;196");
+        assert_eq!(sgr(ColorDepth::Ansi16).unwrap(), "31");
+    }
+
+    #[test]
+    fn thresholds() {
+        let thresholds = ["1=red", "0.1=yellow"]
+            .map(|t| t.parse::<Threshold>().unwrap())
+            .to_vec();
+        let p = Palette::new(1.0, ColorScale::Linear, thresholds, ColorDepth::Truecolor);
+        assert_eq!(p.sgr(0.05, false), None);
+        assert_eq!(p.sgr(0.5, false).unwrap(), "33");
+        assert_eq!(p.sgr(1.5, true).unwrap(), "41");
+
+        assert!("1=mauve".parse::<Threshold>().is_err());
+        assert!("red".parse::<Threshold>().is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use crate::templates::Templates;
+use itertools::Itertools;
+use std::fmt;
+use std::fmt::Formatter;
+use std::time::Duration;
+
+/// Compares the delay attributed to each template in two runs of the same process
+pub struct Diff {
+    old: Templates,
+    new: Templates,
+    count: usize,
+}
+
+impl Diff {
+    pub fn new(old: Templates, new: Templates, count: usize) -> Self {
+        Diff { old, new, count }
+    }
+
+    fn total(templates: &Templates) -> Duration {
+        templates.iter().map(|(_, total, _)| total).sum()
+    }
+
+    /// templates of `run` missing from `other`, slowest first
+    fn write_only(
+        &self,
+        f: &mut Formatter<'_>,
+        name: &str,
+        run: &Templates,
+        other: &Templates,
+    ) -> fmt::Result {
+        let only = run
+            .iter()
+            .filter(|(template, _, _)| other.get(template).is_none())
+            .sorted_by(|(a_name, a, _), (b_name, b, _)| b.cmp(a).then(a_name.cmp(b_name)))
+            .collect::<Vec<_>>();
+        if only.is_empty() {
+            return Ok(());
+        }
+        writeln!(f, "only in {name}:")?;
+        for (template, total, count) in only.iter().take(self.count) {
+            writeln!(f, "{:>12.4} {:>8} {template}", total.as_secs_f32(), count)?;
+        }
+        if only.len() > self.count {
+            writeln!(f, "... and {} more", only.len() - self.count)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let old = Self::total(&self.old).as_secs_f32();
+        let new = Self::total(&self.new).as_secs_f32();
+        writeln!(f, "total {old:.4} -> {new:.4} ({:+.4})", new - old)?;
+
+        let changed = self
+            .new
+            .iter()
+            .filter_map(|(template, new, _)| {
+                let old = self.old.get(template)?;
+                let delta = new.as_secs_f32() - old.as_secs_f32();
+                (delta != 0.0).then_some((template, old, new, delta))
+            })
+            .sorted_by(|(a_name, .., a), (b_name, .., b)| {
+                b.abs().total_cmp(&a.abs()).then(a_name.cmp(b_name))
+            })
+            .take(self.count)
+            .collect::<Vec<_>>();
+        if !changed.is_empty() {
+            writeln!(f, "changed most:")?;
+        }
+        for (template, old, new, delta) in changed {
+            writeln!(
+                f,
+                "{:>12.4} {:>12.4} {:>+12.4} {template}",
+                old.as_secs_f32(),
+                new.as_secs_f32(),
+                delta
+            )?;
+        }
+
+        self.write_only(f, "old", &self.old, &self.new)?;
+        self.write_only(f, "new", &self.new, &self.old)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diff::Diff;
+    use crate::templates::Templates;
+    use std::time::Duration;
+
+    #[test]
+    fn diff_runs() {
+        let mut old = Templates::new(1);
+        old.insert("compile 1", Duration::from_millis(100));
+        old.insert("compile 2", Duration::from_millis(100));
+        old.insert("link", Duration::from_millis(50));
+        old.insert("fetch a", Duration::from_millis(20));
+        old.insert("fetch b", Duration::from_millis(10));
+        let mut new = Templates::new(1);
+        new.insert("compile 1", Duration::from_millis(250));
+        new.insert("link", Duration::from_millis(40));
+        new.insert("test 1", Duration::from_millis(60));
+
+        assert_eq!(
+            Diff::new(old, new, 1).to_string(),
+            "total 0.2800 -> 0.3500 (+0.0700)\n\
+             changed most:\n      \
+                   0.2000       0.2500      +0.0500 compile <num>\n\
+             only in old:\n      \
+                   0.0200        1 fetch a\n\
+             ... and 1 more\n\
+             only in new:\n      \
+                   0.0600        1 test <num>\n"
+        );
+    }
+}
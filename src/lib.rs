@@ -0,0 +1,18 @@
+//! Timing of text lines: delays between lines, from their arrival or from timestamps in them,
+//! and the largest ones with the lines around them.
+
+pub mod analyzer;
+pub mod lines;
+pub mod maximals;
+pub mod stats;
+#[cfg(feature = "tokio")]
+pub mod stream;
+pub mod summary;
+pub mod timer;
+
+pub use analyzer::{Analyzer, AnalyzerOptions};
+pub use lines::{TimeLines, TimeReader};
+pub use maximals::Maximals;
+#[cfg(feature = "tokio")]
+pub use stream::{AsyncTimeReader, TimeStream};
+pub use timer::{Stamp, Timer};
@@ -0,0 +1,150 @@
+use crate::analyzer::Analyzer;
+use crate::timer::{Stamp, Timer};
+use std::io;
+use std::io::BufRead;
+
+/// Lines of a reader with their line endings, decoded lossily when they are not UTF-8 as the
+/// command does
+pub struct ReadLines<R> {
+    reader: R,
+}
+
+impl<R: BufRead> ReadLines<R> {
+    /// next line as read, before decoding
+    fn next_bytes(&mut self) -> Option<io::Result<Vec<u8>>> {
+        let mut line = vec![];
+        match self.reader.read_until(b'\n', &mut line) {
+            Ok(0) => None,
+            Ok(_) => Some(Ok(line)),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ReadLines<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = self.next_bytes()?;
+        Some(line.map(|line| String::from_utf8_lossy(&line).into_owned()))
+    }
+}
+
+/// Timing of lines from any iterator, such as captured output
+pub trait TimeLines: Iterator<Item = String> + Sized {
+    /// lines with their stamps, skipping the ones `timer` finds no time in
+    fn stamped<T: Timer>(self, mut timer: T) -> impl Iterator<Item = (String, Stamp)> {
+        self.filter_map(move |line| {
+            let stamp = timer.stamp(&line)?;
+            Some((line, stamp))
+        })
+    }
+
+    /// analyze every line, given a line ending if it has none, and return `analyzer` once they
+    /// are all analyzed
+    fn analyze(self, mut analyzer: Analyzer) -> Analyzer {
+        for mut line in self {
+            if !line.ends_with('\n') {
+                line.push('\n');
+            }
+            analyzer.insert(&line);
+        }
+        analyzer.finish();
+        analyzer
+    }
+}
+
+impl<I: Iterator<Item = String>> TimeLines for I {}
+
+/// Timing of the lines of a reader, such as the output of a child process
+pub trait TimeReader: BufRead + Sized {
+    /// lines with their line endings
+    fn read_lines(self) -> ReadLines<Self> {
+        ReadLines { reader: self }
+    }
+
+    /// lines with their line endings and their stamps, skipping the ones `timer` finds no time in
+    fn stamped_lines<T: Timer>(
+        self,
+        mut timer: T,
+    ) -> impl Iterator<Item = io::Result<(String, Stamp)>> {
+        self.read_lines().filter_map(move |line| match line {
+            Ok(line) => timer.stamp(&line).map(|stamp| Ok((line, stamp))),
+            Err(e) => Some(Err(e)),
+        })
+    }
+
+    /// analyze every line until the end of the input, and return `analyzer`
+    fn analyze(self, mut analyzer: Analyzer) -> io::Result<Analyzer> {
+        let mut lines = self.read_lines();
+        while let Some(line) = lines.next_bytes() {
+            let line = line?;
+            analyzer.insert_decoded(&String::from_utf8_lossy(&line), line.len());
+        }
+        analyzer.finish();
+        Ok(analyzer)
+    }
+}
+
+impl<R: BufRead> TimeReader for R {}
+
+#[cfg(test)]
+mod tests {
+    use crate::analyzer::{Analyzer, AnalyzerOptions};
+    use crate::lines::{TimeLines, TimeReader};
+    use crate::timer::RegexTimer;
+    use regex::Regex;
+    use std::time::Duration;
+
+    fn timer() -> RegexTimer {
+        RegexTimer::new(Regex::new(r"^(?P<time>\d+)").unwrap(), "%s")
+    }
+
+    #[test]
+    fn stamped() {
+        let lines = ["10 a", "b", "12 c"].map(String::from);
+        let delays = lines
+            .into_iter()
+            .stamped(timer())
+            .map(|(line, stamp)| (line, stamp.last))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            [
+                ("10 a".to_string(), Duration::ZERO),
+                ("12 c".to_string(), Duration::from_secs(2))
+            ]
+        );
+
+        let input = "10 a\n11 b\r\n15 c".as_bytes();
+        let lines = input.stamped_lines(timer()).collect::<Result<Vec<_>, _>>();
+        let lines = lines
+            .unwrap()
+            .into_iter()
+            .map(|(l, _)| l)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, ["10 a\n", "11 b\r\n", "15 c"]);
+
+        let input: &[u8] = b"10 a\xff\n11 b\n";
+        let lines = input.stamped_lines(timer()).map(|line| line.unwrap().0);
+        assert_eq!(lines.collect::<Vec<_>>(), ["10 a\u{fffd}\n", "11 b\n"]);
+    }
+
+    #[test]
+    fn analyze() {
+        let analyzer = || Analyzer::new(Box::new(timer()), AnalyzerOptions::default());
+
+        let lines = ["10 a", "11 b", "15 c"].map(String::from);
+        let from_lines = lines.into_iter().analyze(analyzer());
+        let from_reader = "10 a\n11 b\n15 c\n".as_bytes().analyze(analyzer()).unwrap();
+        for a in [from_lines, from_reader] {
+            let summary = a.maximals().entries().map(|e| e.line()).collect::<Vec<_>>();
+            assert_eq!(summary, ["15 c\n", "11 b\n", "10 a\n"]);
+        }
+
+        // offsets are those of the input, not of the decoded lines
+        let a = b"10 a\xff\n11 b\n".as_slice().analyze(analyzer()).unwrap();
+        let first = a.maximals().entries().next().unwrap();
+        assert_eq!((first.line(), first.position.offset), ("11 b\n", 6));
+    }
+}
@@ -1,26 +1,75 @@
-mod maximals;
-mod timer;
+mod baseline;
+mod browse;
+mod bursts;
+mod color;
+mod diff;
+mod pairs;
+mod prefix;
+mod record;
+mod templates;
+mod tui;
+mod windows;
 
-use crate::maximals::Maximals;
-use crate::timer::{ChronoTimer, RegexTimer, Stamp, Timer};
+use crate::baseline::Baseline;
+use crate::bursts::Bursts;
+use crate::color::{with_colors, ColorChoice, ColorDepth, ColorScale, Palette, Threshold};
+use crate::diff::Diff;
+use crate::pairs::Pairs;
+use crate::prefix::{Field, Layout, Prefix};
+use crate::record::{Clock, Recorder, Recording, ReplayClock, ReplayTimer};
+use crate::templates::Templates;
+use crate::windows::Windows;
+use chrono::format::{Item, StrftimeItems};
+use chrono::Utc;
 use clap::error::ErrorKind;
-use clap::{CommandFactory, Parser};
+use clap::{CommandFactory, Parser, Subcommand};
 use colored::Colorize;
 use itertools::Itertools;
 use regex::Regex;
-use signal_hook::consts::TERM_SIGNALS;
+use signal_hook::consts::{SIGUSR1, TERM_SIGNALS};
 use signal_hook::flag;
-use signal_hook::iterator::Signals;
-use std::collections::VecDeque;
-use std::fmt::Formatter;
-use std::io::BufRead;
-use std::path::PathBuf;
-use std::rc::Rc;
+use signal_hook::iterator::{Handle, Signals};
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::{fmt, fs, io, thread, vec};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+use std::{fs, io, thread, vec};
+use txt_timer::analyzer::{Analyzer, AnalyzerOptions, Attribution, Record, Step};
+use txt_timer::stats::Score;
+use txt_timer::summary::{Position, SummaryOptions};
+use txt_timer::timer::{ChronoTimer, RegexTimer, Stamp, Timer};
 
-#[derive(Parser)]
+/// how often signals are checked while waiting for input
+const POLL: Duration = Duration::from_millis(100);
+
+/// z-score colored red, half of it yellow
+const ANOMALY_RANGE: f32 = 6.0;
+
+#[derive(Clone, Subcommand)]
+enum Command {
+    /// read lines from a recording made with `--record` instead of standard input, timed with
+    /// their recorded arrival unless a time regex is given
+    Replay {
+        /// recording in asciinema cast v2 format
+        file: PathBuf,
+        /// re-emit lines with their recorded delays divided by this factor, instead of at once
+        #[clap(long, value_parser)]
+        speed: Option<f32>,
+    },
+    /// compare two runs by the total delay of each template, listing the templates whose time
+    /// changed most and those present in only one run
+    Diff {
+        /// recording, or log timed with the time regex
+        old: PathBuf,
+        /// recording, or log timed with the time regex
+        new: PathBuf,
+    },
+}
+
+#[derive(Clone, Parser)]
 /// Pipe through standard input while highlighting and keeping track of delays between lines.
 ///
 /// When completed print summary of maximum delays
@@ -31,11 +80,34 @@ struct Cli {
     /// number of top differences to print at the end
     #[clap(short, long, value_parser, default_value_t = 5)]
     count: usize,
+    /// number of lines of context to keep before each maximal
     #[clap(short = 'B', long, value_parser, default_value_t = 5)]
     lines_before: usize,
+    /// number of lines of context to keep after each maximal
+    #[clap(short = 'A', long, value_parser, default_value_t = 0)]
+    lines_after: usize,
+    /// number of lines of context to keep before and after each maximal, overrides -A and -B
+    #[clap(short = 'C', long, value_parser)]
+    context: Option<usize>,
     /// range for color scale of delay, in seconds
     #[clap(long, value_parser, default_value_t = 0.2)]
     color_range: f32,
+    /// when to color the output, files are colored only with `always`
+    #[clap(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+    /// spread of delays over the color range
+    #[clap(long, value_enum, default_value_t = ColorScale::Linear)]
+    color_scale: ColorScale,
+    /// color delays from SECONDS with a named color instead of the color range, as
+    /// SECONDS=COLOR, can be repeated
+    #[clap(long, value_parser)]
+    color_threshold: Vec<Threshold>,
+    /// colors supported by the terminal
+    #[clap(long, value_enum, default_value_t = ColorDepth::Auto)]
+    color_depth: ColorDepth,
+    /// color the background of the whole line with the color of its delay
+    #[clap(long, value_parser, default_value_t = false)]
+    color_line: bool,
     /// use regex to extract timestamp from lines instead of using real time, expecting iso8601=ms
     /// YYYY-mm-ddTHH-MM-SS.3fZ
     #[clap(long, value_parser)]
@@ -46,105 +118,308 @@ struct Cli {
     /// format of timestamp, without timezone see `strftime`. Example `%Y-%m-%d %H:%M:%S%.3f`
     #[clap(long, value_parser)]
     time_regex_format: Option<String>,
+    /// only time lines matching one of these regexes, other lines are passed through without
+    /// affecting delays
+    #[clap(long, value_parser)]
+    only: Vec<Regex>,
+    /// do not time lines matching one of these regexes, they are passed through without
+    /// affecting delays
+    #[clap(long, value_parser)]
+    ignore: Vec<Regex>,
+    /// lines not matching this regex are continuations of the previous record, e.g. stack traces,
+    /// and are timed and kept in maximals together with it
+    #[clap(long, value_parser)]
+    record_start: Option<Regex>,
+    /// show line number and byte offset of lines in prepended time and maximals
+    #[clap(short = 'n', long, value_parser, default_value_t = false)]
+    line_number: bool,
+    /// attribute delays to the line before or after them, for ranking and highlighting
+    #[clap(long, value_enum, default_value_t = Attribution::After)]
+    attribute: Attribution,
     /// prepend time to output
     #[clap(short, long, value_parser, default_value_t = false)]
     prepend_time: bool,
+    /// where the time of lines is printed, `inline` and `ts` imply `--prepend-time`
+    #[clap(long, value_enum, default_value_t = Layout::Separate)]
+    stamp_layout: Layout,
+    /// parts of the time of lines printed, comma separated
+    #[clap(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = [Field::Delta, Field::Elapsed, Field::Wall]
+    )]
+    stamp_fields: Vec<Field>,
+    /// strftime format of the wall time, instead of rfc3339
+    #[clap(long, value_parser)]
+    wall_format: Option<String>,
+    /// print the wall time in the local time zone instead of UTC
+    #[clap(long, value_parser, default_value_t = false)]
+    local_time: bool,
+    /// print maximals with overlapping context as a single block, after the ranking by delay
+    #[clap(short, long, value_parser, default_value_t = false)]
+    merge_contexts: bool,
+    /// print statistics of delays at the end: percentiles, histogram and time over color range
+    #[clap(short, long, value_parser, default_value_t = false)]
+    stats: bool,
+    /// print the `count` message templates, with numbers, hex, uuids and quoted strings masked,
+    /// with most total delay and occurrences
+    #[clap(long, value_parser, default_value_t = false)]
+    templates: bool,
+    /// save the total delay of each template to a file, as baseline of a known-good run
+    #[clap(long, value_parser)]
+    save_baseline: Option<PathBuf>,
+    /// compare the total delay of each template with a file saved by `--save-baseline` and print
+    /// the regressions
+    #[clap(long, value_parser)]
+    baseline: Option<PathBuf>,
+    /// percentage a template must exceed its baseline by to be a regression
+    #[clap(long, value_parser, default_value_t = 20.0)]
+    regression_threshold: f32,
+    /// exit with failure when there are regressions against the baseline
+    #[clap(long, value_parser, default_value_t = false, requires = "baseline")]
+    fail_on_regression: bool,
+    /// measure latency between lines matching this regex and `--pair-end`, must have one
+    /// (?<id> ) named capturing group identifying the pair
+    #[clap(long, value_parser)]
+    pair_start: Option<Regex>,
+    /// end of pairs started by `--pair-start`, must have one (?<id> ) named capturing group
+    #[clap(long, value_parser)]
+    pair_end: Option<Regex>,
+    /// full screen dashboard with the scrolling input, top maximals and line rate, the summary
+    /// is printed after quitting with `q`. SIGUSR1 prints no summary while it is shown
+    #[clap(long, value_parser, default_value_t = false)]
+    tui: bool,
+    /// keep the whole input and browse it interactively from the maximals when it ends, before
+    /// printing the summary
+    #[clap(long, value_parser, default_value_t = false)]
+    browse: bool,
+    /// also keep the maximals of consecutive windows of this many seconds, aligned on the time of
+    /// the lines, and print the `--windows` last ones
+    #[clap(long, value_parser)]
+    window: Option<f32>,
+    /// number of windows kept
+    #[clap(long, value_parser, default_value_t = 6, requires = "window")]
+    windows: usize,
+    /// rank and color delays by how anomalous they are compared to the moving mean and
+    /// deviation of the delays before them (z-score), rather than by their duration
+    #[clap(long, value_parser, default_value_t = false)]
+    anomaly: bool,
+    /// weight of each new delay in the moving mean and deviation of `--anomaly`, smaller adapts
+    /// slower, the first 1/alpha delays are not scored
+    #[clap(long, value_parser, default_value_t = 0.05, requires = "anomaly")]
+    anomaly_alpha: f64,
+    /// print the intervals with most lines and the longest runs of near-zero delays
+    #[clap(long, value_parser, default_value_t = false)]
+    bursts: bool,
+    /// seconds of the intervals lines are counted in for `--bursts`, aligned on the time of the
+    /// lines
+    #[clap(long, value_parser, default_value_t = 1.0, requires = "bursts")]
+    burst_interval: f32,
+    /// delay in seconds under which consecutive lines are a run for `--bursts`
+    #[clap(long, value_parser, default_value_t = 0.001, requires = "bursts")]
+    burst_gap: f32,
     /// redirect output of maximum differences to a file
     #[clap(short, long, value_parser)]
     output_maximals: Option<PathBuf>,
+    /// also print the summary so far every this many seconds, to standard error or rewriting
+    /// the `--output-maximals` file, as on SIGUSR1, not with `--tui`
+    #[clap(long, value_parser)]
+    summary_interval: Option<f32>,
+    /// start the maximals, statistics and templates over after each interval summary
+    #[clap(
+        long,
+        value_parser,
+        default_value_t = false,
+        requires = "summary_interval"
+    )]
+    summary_reset: bool,
+    /// record every line with its arrival time to a file, in asciinema cast v2 format
+    #[clap(long, value_parser)]
+    record: Option<PathBuf>,
+    /// pass the input unchanged to standard output, and write the times, the lines and the
+    /// summary to `stderr` or to a file instead
+    #[clap(long, value_parser, value_name = "stderr|FILE")]
+    annotate_to: Option<PathBuf>,
+    #[clap(subcommand)]
+    command: Option<Command>,
 }
 
 impl Cli {
     fn parse_and_validate() -> Cli {
-        let cli = Cli::parse();
+        let mut cli = Cli::parse();
+
+        if let Some(c) = cli.context {
+            cli.lines_before = c;
+            cli.lines_after = c;
+        }
+
+        check_seconds(cli.color_range, "color range");
+
+        if cli.stamp_layout != Layout::Separate {
+            cli.prepend_time = true;
+            if matches!(cli.attribute, Attribution::Before) {
+                Cli::command()
+                    .error(
+                        ErrorKind::ArgumentConflict,
+                        "inline stamps need the delay before printing the line, with --attribute after",
+                    )
+                    .exit();
+            }
+        }
+
+        if cli.line_number && !cli.stamp_fields.contains(&Field::Line) {
+            cli.stamp_fields.push(Field::Line);
+        }
+
+        if let Some(format) = &cli.wall_format {
+            if StrftimeItems::new(format).any(|i| i == Item::Error) {
+                Cli::command()
+                    .error(
+                        ErrorKind::InvalidValue,
+                        format!("invalid strftime format `{format}`"),
+                    )
+                    .exit();
+            }
+        }
 
-        if cli.color_range <= 0.0 {
+        if cli.color_line && matches!(cli.attribute, Attribution::Before) {
             Cli::command()
-                .error(ErrorKind::InvalidValue, "color range must be positive")
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "--color-line needs the delay before printing the line, with --attribute after",
+                )
                 .exit();
         }
 
-        cli
-    }
-}
+        if let Some(window) = cli.window {
+            check_seconds(window, "window");
+        }
+        if cli.windows == 0 {
+            Cli::command()
+                .error(
+                    ErrorKind::InvalidValue,
+                    "the number of windows must be positive",
+                )
+                .exit();
+        }
 
-#[derive(Eq, PartialEq, Ord, PartialOrd)]
-struct MaximalsStampsEntry {
-    stamp: Stamp,
-    lines: Vec<Rc<str>>,
-}
+        if !(cli.anomaly_alpha > 0.0 && cli.anomaly_alpha <= 1.0) {
+            Cli::command()
+                .error(ErrorKind::InvalidValue, "anomaly alpha must be in (0, 1]")
+                .exit();
+        }
 
-impl fmt::Display for MaximalsStampsEntry {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        writeln!(
-            f,
-            "Δ{:.4} @{:.4}",
-            self.stamp.last.as_secs_f32(),
-            self.stamp.total.as_secs_f32()
-        )?;
+        check_seconds(cli.burst_interval, "burst interval");
+        check_seconds(cli.burst_gap, "burst gap");
 
-        for l in &self.lines {
-            write!(f, "{l}")?;
+        if let Some(interval) = cli.summary_interval {
+            check_seconds(interval, "summary interval");
+            if cli.tui {
+                Cli::command()
+                    .error(
+                        ErrorKind::ArgumentConflict,
+                        "--summary-interval does not support --tui",
+                    )
+                    .exit();
+            }
         }
-        Ok(())
-    }
-}
 
-struct MaximalsStampsBuffer {
-    max: Maximals<MaximalsStampsEntry>,
-    lines: VecDeque<Rc<str>>,
-    lines_count: usize,
-}
+        if let Some(Command::Replay { speed, .. }) = &cli.command {
+            if speed.is_some_and(|s| !(s > 0.0 && s.is_finite())) {
+                Cli::command()
+                    .error(
+                        ErrorKind::InvalidValue,
+                        "replay speed must be a positive number",
+                    )
+                    .exit();
+            }
+            if cli.tui {
+                Cli::command()
+                    .error(ErrorKind::ArgumentConflict, "replay does not support --tui")
+                    .exit();
+            }
+        }
 
-impl MaximalsStampsBuffer {
-    fn new(count: usize, c: usize) -> Self {
-        MaximalsStampsBuffer {
-            max: Maximals::new(count),
-            lines: VecDeque::with_capacity(c),
-            lines_count: c,
+        if matches!(cli.command, Some(Command::Diff { .. }))
+            && (cli.tui || cli.browse || cli.record.is_some())
+        {
+            Cli::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "diff does not support --tui, --browse or --record",
+                )
+                .exit();
+        }
+
+        if cli.annotate_to.is_some() && (cli.tui || cli.browse) {
+            Cli::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "--annotate-to does not support --tui or --browse",
+                )
+                .exit();
         }
+
+        cli
     }
 
-    fn insert(&mut self, stamp: Stamp, value: &str) {
-        self.lines.push_back(Rc::from(value));
-        if self.lines.len() > self.lines_count + 1 {
-            self.lines.pop_front();
+    /// whether the annotated output goes to a terminal, for coloring it
+    fn annotated_terminal(&self) -> bool {
+        match self.annotate_to.as_deref() {
+            None => io::stdout().is_terminal(),
+            Some(path) if path == Path::new("stderr") => io::stderr().is_terminal(),
+            Some(_) => false,
         }
+    }
 
-        if let Some(b) = self.max.insert(MaximalsStampsEntry {
-            stamp,
-            lines: vec![],
-        }) {
-            b.lines.extend(self.lines.iter().cloned());
-        };
+    /// where the times, the lines and the summary are written
+    fn annotated_output(&self) -> io::Result<Box<dyn io::Write>> {
+        Ok(match self.annotate_to.as_deref() {
+            None => Box::new(io::stdout().lock()),
+            Some(path) if path == Path::new("stderr") => Box::new(io::stderr().lock()),
+            Some(path) => Box::new(io::BufWriter::new(fs::File::create(path)?)),
+        })
     }
 }
 
-impl fmt::Display for MaximalsStampsBuffer {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        for e in self.max.iter() {
-            writeln!(f, "{e}")?;
-            writeln!(f)?;
-        }
-        Ok(())
+/// exit with an error unless `seconds` is positive and fits in a `Duration`
+fn check_seconds(seconds: f32, name: &str) {
+    if !(seconds > 0.0 && Duration::try_from_secs_f32(seconds).is_ok()) {
+        Cli::command()
+            .error(
+                ErrorKind::InvalidValue,
+                format!("{name} must be a positive number of seconds, got {seconds}"),
+            )
+            .exit();
     }
 }
 
-fn print_stamp<T: io::Write>(cli: &Cli, stamp: &Stamp, writer: &mut T) -> io::Result<()> {
-    if cli.prepend_time {
-        let x = stamp.last.as_secs_f32();
-        let x_scale = x / cli.color_range;
-        let r: u8 = (255.0 * (2.0 * x_scale)).clamp(0.0, 255.0) as u8;
-        let g: u8 = (255.0 * (2.0 - 2.0 * x_scale)).clamp(0.0, 255.0) as u8;
-        writeln!(
-            writer,
-            "Δ{} @{} {}",
-            format!("{x:.4}").truecolor(r, g, 0),
-            format!("{:.4}", stamp.total.as_secs_f32()).blue(),
-            stamp.utc.to_rfc3339().bold().white()
-        )
+/// value the color of a delay is picked with: its z-score when ranking by anomaly
+fn shade(cli: &Cli, stamp: &Stamp, score: Score) -> f32 {
+    if cli.anomaly {
+        score.0
     } else {
-        Ok(())
+        stamp.last.as_secs_f32()
+    }
+}
+
+/// print the time of a line with `--prepend-time`
+fn print_stamp<T: io::Write>(
+    cli: &Cli,
+    prefix: Option<&Prefix>,
+    palette: &Palette,
+    stamp: &Stamp,
+    score: Score,
+    position: Position,
+    writer: &mut T,
+) -> io::Result<()> {
+    match prefix {
+        Some(prefix) => {
+            let shade = shade(cli, stamp, score);
+            prefix.write(writer, palette, stamp, score, shade, position)
+        }
+        None => Ok(()),
     }
 }
 
@@ -182,79 +457,575 @@ fn make_timer(cli: &mut Cli) -> Box<dyn Timer> {
     }
 }
 
+fn make_pairs(cli: &mut Cli) -> Option<Pairs> {
+    match (cli.pair_start.take(), cli.pair_end.take()) {
+        (Some(start), Some(end)) => {
+            if ![&start, &end]
+                .iter()
+                .all(|r| r.capture_names().contains(&Some("id")))
+            {
+                Cli::command()
+                    .error(
+                        ErrorKind::InvalidValue,
+                        "pair regexes must have a `(?P<id>exp)` capturing group",
+                    )
+                    .exit();
+            }
+            Some(Pairs::new(start, end, cli.count))
+        }
+        (None, None) => None,
+        _ => Cli::command()
+            .error(
+                ErrorKind::InvalidValue,
+                "pair start and end must be either both present or absent",
+            )
+            .exit(),
+    }
+}
+
 struct Handler {
-    timer: Box<dyn Timer>,
-    max: MaximalsStampsBuffer,
+    analyzer: Analyzer,
+    templates: Option<Templates>,
+    pairs: Option<Pairs>,
+    windows: Option<Windows>,
+    bursts: Option<Bursts>,
+    palette: Palette,
+    prefix: Option<Prefix>,
     cli: Cli,
+    /// every line with its delay, kept only for browsing
+    history: Vec<(Option<Duration>, String)>,
+    /// arrival time of lines for the recording
+    clock: Clock,
+    recorder: Option<Recorder>,
+    baseline: Option<Baseline>,
+    /// when the next interval summary is due
+    next_report: Option<Instant>,
+    /// set on SIGUSR1
+    report_flag: Arc<AtomicBool>,
+    /// receives the input unchanged when annotations go elsewhere
+    passthrough: Option<io::Stdout>,
 }
 
 impl Handler {
     fn new(mut cli: Cli) -> Self {
-        let max = MaximalsStampsBuffer::new(cli.count, cli.lines_before);
+        let templates = Self::templates(&cli);
 
-        let timer = make_timer(&mut cli);
+        let pairs = make_pairs(&mut cli);
 
-        Handler { timer, max, cli }
-    }
+        let windows = cli
+            .window
+            .map(|w| Windows::new(Duration::from_secs_f32(w), cli.windows, cli.count));
 
-    fn process_line<T: io::Write>(&mut self, buffer: &str, writer: &mut T) -> io::Result<()> {
-        if let Some(stamp) = self.timer.stamp(buffer) {
-            print_stamp(&self.cli, &stamp, writer)?;
-            self.max.insert(stamp, buffer);
+        let bursts = cli.bursts.then(|| {
+            Bursts::new(
+                Duration::from_secs_f32(cli.burst_interval),
+                Duration::from_secs_f32(cli.burst_gap),
+                cli.count,
+            )
+        });
+
+        let palette = Palette::new(
+            if cli.anomaly {
+                ANOMALY_RANGE
+            } else {
+                cli.color_range
+            },
+            cli.color_scale,
+            std::mem::take(&mut cli.color_threshold),
+            cli.color_depth,
+        );
+
+        let prefix = cli.prepend_time.then(|| {
+            Prefix::new(
+                cli.stamp_layout,
+                std::mem::take(&mut cli.stamp_fields),
+                cli.wall_format.take(),
+                cli.local_time,
+                cli.anomaly,
+                cli.quiet,
+            )
+        });
+
+        let options = AnalyzerOptions {
+            count: cli.count,
+            lines_before: cli.lines_before,
+            lines_after: cli.lines_after,
+            summary: SummaryOptions {
+                merge_contexts: cli.merge_contexts,
+                line_number: cli.line_number,
+                anomaly: cli.anomaly,
+            },
+            attribute: cli.attribute,
+            record_start: cli.record_start.take(),
+            only: std::mem::take(&mut cli.only),
+            ignore: std::mem::take(&mut cli.ignore),
+            anomaly_alpha: cli.anomaly.then_some(cli.anomaly_alpha),
+            stats: cli.stats.then(|| Duration::from_secs_f32(cli.color_range)),
         };
+        let analyzer = Analyzer::new(make_timer(&mut cli), options);
+
+        let next_report = cli
+            .summary_interval
+            .and_then(|i| Instant::now().checked_add(Duration::from_secs_f32(i)));
+
+        Handler {
+            analyzer,
+            templates,
+            pairs,
+            windows,
+            bursts,
+            palette,
+            prefix,
+            cli,
+            history: vec![],
+            clock: Clock::Real(Instant::now()),
+            recorder: None,
+            baseline: None,
+            next_report,
+            report_flag: Arc::new(AtomicBool::new(false)),
+            passthrough: None,
+        }
+    }
+
+    /// empty templates, when needed
+    fn templates(cli: &Cli) -> Option<Templates> {
+        (cli.templates || cli.save_baseline.is_some() || cli.baseline.is_some())
+            .then(|| Templates::new(cli.count))
+    }
+
+    /// returns the stamp of the line, if it was timed. The line is printed as read, it is
+    /// decoded lossily only to time and annotate it.
+    fn process_line<T: io::Write>(
+        &mut self,
+        raw: &[u8],
+        writer: &mut T,
+    ) -> io::Result<Option<Stamp>> {
+        if let Some(passthrough) = &mut self.passthrough {
+            passthrough.write_all(raw)?;
+            passthrough.flush()?;
+        }
+        let buffer = &*String::from_utf8_lossy(raw);
+        if let Some(recorder) = &mut self.recorder {
+            recorder.write(self.clock.elapsed(), buffer)?;
+        }
+
+        let step = self.analyzer.insert_decoded(buffer, raw.len());
+        self.apply(&step, writer)?;
+        if let Some(prefix) = self.prefix.as_ref().filter(|_| step.annotation.is_none()) {
+            prefix.write_blank(writer)?;
+        }
+        let background = step.stamp.map(|s| shade(&self.cli, &s, step.score));
+
+        if self.cli.browse {
+            self.history.push((
+                step.stamp.map(|s| s.last),
+                buffer.trim_end_matches(['\r', '\n']).to_string(),
+            ));
+        }
         if !self.cli.quiet {
-            write!(writer, "{buffer}")?;
+            match background.filter(|_| self.cli.color_line) {
+                Some(shade) => {
+                    let line = buffer.trim_end_matches(['\r', '\n']);
+                    let end = &buffer[line.len()..];
+                    write!(writer, "{}{end}", self.palette.paint(line, shade, true))?;
+                }
+                None => writer.write_all(raw)?,
+            }
+        }
+        writer.flush()?;
+        Ok(step.stamp)
+    }
+
+    /// print the time the analyzer attributed to a line, and analyze the completed records
+    fn apply<T: io::Write>(&mut self, step: &Step, writer: &mut T) -> io::Result<()> {
+        if let Some(a) = &step.annotation {
+            print_stamp(
+                &self.cli,
+                self.prefix.as_ref(),
+                &self.palette,
+                &a.stamp,
+                a.score,
+                a.position,
+                writer,
+            )?;
+        }
+        for record in &step.records {
+            self.commit(record);
+        }
+        Ok(())
+    }
+
+    fn commit(&mut self, record: &Record) {
+        let Record {
+            stamp,
+            position,
+            text,
+            ..
+        } = record;
+        let first_line = text.lines().next().unwrap_or_default();
+        if let Some(templates) = &mut self.templates {
+            templates.insert(first_line, stamp.last);
+        }
+        if let Some(pairs) = &mut self.pairs {
+            pairs.insert(text, stamp.total);
+        }
+        if let Some(windows) = &mut self.windows {
+            windows.insert(stamp, position.line, first_line);
+        }
+        if let Some(bursts) = &mut self.bursts {
+            bursts.insert(stamp, position.line, first_line);
+        }
+    }
+
+    /// optional summary sections printed after the maximals
+    fn sections(&self) -> Vec<(&'static str, String)> {
+        let mut sections = vec![];
+        if let Some(windows) = &self.windows {
+            sections.push(("Windows", windows.to_string()));
+        }
+        if let Some(stats) = self.analyzer.stats() {
+            sections.push(("Statistics", stats.to_string()));
+        }
+        if let Some(templates) = self.templates.as_ref().filter(|_| self.cli.templates) {
+            sections.push(("Templates", templates.to_string()));
+        }
+        if let Some(pairs) = &self.pairs {
+            sections.push(("Pairs", pairs.to_string()));
+        }
+        if let Some(bursts) = &self.bursts {
+            sections.push(("Bursts", bursts.to_string()));
+        }
+        sections
+    }
+
+    /// the input ended, analyze the records still waiting for continuations or delays
+    fn finish<T: io::Write>(&mut self, writer: &mut T) -> io::Result<()> {
+        let step = self.analyzer.finish();
+        self.apply(&step, writer)?;
+        if let Some(bursts) = &mut self.bursts {
+            bursts.finish();
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.flush()?;
+        }
+        Ok(())
+    }
+
+    /// print the maximals and sections, or write them to the `--output-maximals` file
+    fn write_summary<T: io::Write>(
+        &self,
+        writer: &mut T,
+        sections: Vec<(&'static str, String)>,
+    ) -> io::Result<()> {
+        match &self.cli.output_maximals {
+            None => {
+                writeln!(
+                    writer,
+                    "\n{}:\n{}",
+                    "Maximals".yellow().bold(),
+                    self.analyzer.maximals()
+                )?;
+                for (name, section) in sections {
+                    writeln!(writer, "{}:\n{}", name.yellow().bold(), section)?;
+                }
+                Ok(())
+            }
+            Some(filename) => {
+                let mut summary = format!("{}", self.analyzer.maximals());
+                with_colors(self.cli.color.enabled(false), || {
+                    for (name, section) in sections {
+                        summary.push_str(&format!("{}:\n{section}\n", name.yellow().bold()));
+                    }
+                });
+                fs::write(filename, summary)
+            }
+        }
+    }
+
+    /// summary so far to standard error or the `--output-maximals` file, when requested by
+    /// SIGUSR1 or when the summary interval elapsed. Returns how long until the next one is due.
+    fn report(&mut self) -> io::Result<Option<Duration>> {
+        let now = Instant::now();
+        let due = self.next_report.is_some_and(|t| now >= t);
+        if self.report_flag.swap(false, Ordering::Relaxed) || due {
+            let mut stderr = io::stderr().lock();
+            let colors = self.cli.color.enabled(stderr.is_terminal());
+            with_colors(colors, || self.write_summary(&mut stderr, self.sections()))?;
+        }
+        if due {
+            if self.cli.summary_reset {
+                self.analyzer.reset();
+                self.templates = Self::templates(&self.cli);
+            }
+            let interval = Duration::from_secs_f32(self.cli.summary_interval.unwrap_or(1.0));
+            self.next_report = now.checked_add(interval);
+        }
+        Ok(self.next_report.map(|t| t.saturating_duration_since(now)))
+    }
+
+    fn print_and_end<T: io::Write>(mut self, writer: &mut T) -> io::Result<ExitCode> {
+        self.finish(writer)?;
+        if self.cli.browse {
+            writer.flush()?;
+            browse::run(&self)?;
+        }
+
+        let mut sections = self.sections();
+        let mut regressed = false;
+        if let Some(templates) = &self.templates {
+            if let Some(path) = &self.cli.save_baseline {
+                Baseline::save(templates, path)?;
+            }
+            if let Some(baseline) = &self.baseline {
+                let regressions =
+                    baseline.regressions(templates, self.cli.regression_threshold, self.cli.count);
+                regressed = !regressions.is_empty();
+                sections.push(("Regressions", regressions.to_string()));
+            }
+        }
+
+        self.write_summary(writer, sections)?;
+
+        if regressed && self.cli.fail_on_regression {
+            Ok(ExitCode::FAILURE)
+        } else {
+            Ok(ExitCode::SUCCESS)
         }
-        writer.flush()
     }
+}
 
-    fn print_and_end<T: io::Write>(self, writer: &mut T) -> io::Result<()> {
-        let max = self.max;
-        let cli = self.cli;
-        match cli.output_maximals {
-            None => writeln!(writer, "\n{}:\n{}", "Maximals".yellow().bold(), max),
-            Some(filename) => fs::write(filename, format!("{max}")),
+/// read the bytes of the lines of standard input on a separate thread, until the end or the
+/// first error
+fn spawn_reader() -> mpsc::Receiver<io::Result<Vec<u8>>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut stdin = io::stdin().lock();
+        loop {
+            let mut buffer = vec![];
+            let line = match stdin.read_until(b'\n', &mut buffer) {
+                Ok(0) => break,
+                Ok(_) => Ok(buffer),
+                Err(e) => Err(e),
+            };
+            let failed = line.is_err();
+            if sender.send(line).is_err() || failed {
+                break;
+            }
         }
+    });
+    receiver
+}
+
+/// process the lines of a recording, timed with their recorded arrival unless a time regex is given
+fn replay<T: io::Write>(
+    handler: &mut Handler,
+    recording: Recording<io::BufReader<fs::File>>,
+    speed: Option<f32>,
+    recorded_time: bool,
+    writer: &mut T,
+    term_flag: &AtomicBool,
+) -> io::Result<()> {
+    let clock = ReplayClock::default();
+    handler.clock = Clock::Replay(clock.clone());
+    if recorded_time {
+        handler
+            .analyzer
+            .set_timer(Box::new(ReplayTimer::new(clock.clone(), recording.begin)));
     }
+
+    let start = Instant::now();
+    for line in recording {
+        let (time, buffer) = line?;
+        if let Some(speed) = speed {
+            // lines due beyond the representable future are never reached
+            let due = Duration::try_from_secs_f32(time.as_secs_f32() / speed)
+                .ok()
+                .and_then(|t| start.checked_add(t));
+            thread::sleep(due.map_or(Duration::MAX, |due| {
+                due.saturating_duration_since(Instant::now())
+            }));
+        }
+        if term_flag.load(Ordering::Relaxed) {
+            break;
+        }
+        clock.set(time);
+        handler.process_line(buffer.as_bytes(), writer)?;
+        handler.report()?;
+    }
+    Ok(())
 }
 
-fn read_and_process(cli: Cli, term_flag: Arc<AtomicBool>) -> io::Result<()> {
+/// templates of a recording, or of a log timed with the time regex
+fn analyze(
+    cli: &Cli,
+    path: &Path,
+    recorded_time: bool,
+    term_flag: &AtomicBool,
+) -> io::Result<Templates> {
+    let mut handler = Handler::new(Cli {
+        templates: true,
+        ..cli.clone()
+    });
+    let mut sink = io::sink();
+
+    match Recording::open(path) {
+        Ok(recording) => replay(
+            &mut handler,
+            recording,
+            None,
+            recorded_time,
+            &mut sink,
+            term_flag,
+        )?,
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+            if recorded_time {
+                Cli::command()
+                    .error(
+                        ErrorKind::MissingRequiredArgument,
+                        format!(
+                            "{} is not a recording, a time regex is needed to time its lines",
+                            path.display()
+                        ),
+                    )
+                    .exit();
+            }
+            let mut reader = io::BufReader::new(fs::File::open(path)?);
+            let mut buffer = vec![];
+            while !term_flag.load(Ordering::Relaxed) && reader.read_until(b'\n', &mut buffer)? > 0 {
+                handler.process_line(&buffer, &mut sink)?;
+                buffer.clear();
+            }
+        }
+        Err(e) => return Err(e),
+    }
+
+    handler.finish(&mut sink)?;
+    Ok(handler
+        .templates
+        .take()
+        .unwrap_or_else(|| Templates::new(cli.count)))
+}
+
+fn diff(
+    cli: Cli,
+    old: &Path,
+    new: &Path,
+    recorded_time: bool,
+    term_flag: &AtomicBool,
+) -> io::Result<ExitCode> {
+    let old = analyze(&cli, old, recorded_time, term_flag)?;
+    let new = analyze(&cli, new, recorded_time, term_flag)?;
+    let diff = Diff::new(old, new, cli.count);
+    match cli.output_maximals {
+        None => {
+            println!("{}:\n{}", "Diff".yellow().bold(), diff);
+        }
+        Some(filename) => {
+            let header = with_colors(cli.color.enabled(false), || {
+                "Diff".yellow().bold().to_string()
+            });
+            fs::write(filename, format!("{header}:\n{diff}"))?
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn read_and_process(
+    mut cli: Cli,
+    term_flag: Arc<AtomicBool>,
+    report_flag: Arc<AtomicBool>,
+) -> io::Result<ExitCode> {
+    let recorded_time = cli.time_regex.is_none() && !cli.time_regex_iso;
+    let recording = match cli.command.take() {
+        Some(Command::Replay { file, speed }) => Some((Recording::open(&file)?, speed)),
+        Some(Command::Diff { old, new }) => {
+            return diff(cli, &old, &new, recorded_time, &term_flag);
+        }
+        None => None,
+    };
+    let record = cli.record.take();
+    let baseline = cli.baseline.as_deref().map(Baseline::load).transpose()?;
+
     let mut handler = Handler::new(cli);
+    handler.baseline = baseline;
+    handler.report_flag = report_flag;
+    if handler.cli.annotate_to.is_some() {
+        handler.passthrough = Some(io::stdout());
+    }
+
+    if let Some(path) = record {
+        let begin = recording.as_ref().map_or_else(Utc::now, |(r, _)| r.begin);
+        handler.recorder = Some(Recorder::create(&path, begin)?);
+    }
+
+    if let Some((recording, speed)) = recording {
+        let mut output = handler.cli.annotated_output()?;
+        replay(
+            &mut handler,
+            recording,
+            speed,
+            recorded_time,
+            &mut output,
+            &term_flag,
+        )?;
+        return handler.print_and_end(&mut output);
+    }
+
+    if handler.cli.tui {
+        let handler = tui::run(handler, spawn_reader(), &term_flag)?;
+        return handler.print_and_end(&mut io::stdout().lock());
+    }
+
+    let lines = spawn_reader();
+    let mut output = handler.cli.annotated_output()?;
+
+    while !term_flag.load(Ordering::Relaxed) {
+        let timeout = handler.report()?.map_or(POLL, |t| t.min(POLL));
+        match lines.recv_timeout(timeout) {
+            Ok(line) => {
+                handler.process_line(&line?, &mut output)?;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    handler.print_and_end(&mut output)
+}
 
-    let mut buffer = String::new();
-    let mut stdin = io::stdin().lock();
-    let mut stdout = io::stdout().lock();
+/// stops waiting for signals once processing ended, even by panicking
+struct CloseOnDrop(Handle);
 
-    while !term_flag.load(Ordering::Relaxed) && stdin.read_line(&mut buffer)? > 0 {
-        handler.process_line(&buffer, &mut stdout)?;
-        buffer.clear();
+impl Drop for CloseOnDrop {
+    fn drop(&mut self) {
+        self.0.close();
     }
-    handler.print_and_end(&mut stdout)
 }
 
-fn main() -> io::Result<()> {
+fn main() -> io::Result<ExitCode> {
     let cli: Cli = Cli::parse_and_validate();
+    colored::control::set_override(cli.color.enabled(cli.annotated_terminal()));
 
     let term = Arc::new(AtomicBool::new(false));
+    let report = Arc::new(AtomicBool::new(false));
 
     for sig in TERM_SIGNALS {
         flag::register_conditional_shutdown(*sig, 1, Arc::clone(&term))?;
         flag::register(*sig, Arc::clone(&term))?;
     }
+    flag::register(SIGUSR1, Arc::clone(&report))?;
 
     let mut signals = Signals::new(TERM_SIGNALS)?;
 
-    let signals_handle = signals.handle();
+    let signals_handle = CloseOnDrop(signals.handle());
 
-    let join_handle = thread::spawn(move || -> io::Result<()> {
-        let rv = read_and_process(cli, term);
-        signals_handle.close();
-        rv
+    let join_handle = thread::spawn(move || -> io::Result<ExitCode> {
+        let _close = signals_handle;
+        read_and_process(cli, term, report)
     });
 
     signals.wait();
 
     join_handle
         .join()
-        .expect("waiting processing thread failed")
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
 }
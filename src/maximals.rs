@@ -34,6 +34,11 @@ impl<T: Ord> Maximals<T> {
         self.data.iter()
     }
 
+    /// elements must not be modified in a way that changes their ordering
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> + '_ {
+        self.data.iter_mut()
+    }
+
     #[cfg(test)]
     pub fn clear(&mut self) {
         self.data.clear();
@@ -49,20 +54,20 @@ mod tests {
     #[test]
     fn maximals_int() {
         let mut m: Maximals<u8> = Maximals::new(4);
-        itertools::assert_equal(m.iter(), vec![].iter());
+        itertools::assert_equal(m.iter(), [0u8; 0].iter());
 
         m.insert(1);
-        itertools::assert_equal(m.iter(), (vec![1]).iter());
+        itertools::assert_equal(m.iter(), [1].iter());
         m.insert(2);
         m.insert(3);
         m.insert(7);
-        itertools::assert_equal(m.iter(), vec![7, 3, 2, 1].iter());
+        itertools::assert_equal(m.iter(), [7, 3, 2, 1].iter());
         m.insert(9);
-        itertools::assert_equal(m.iter(), vec![9, 7, 3, 2].iter());
+        itertools::assert_equal(m.iter(), [9, 7, 3, 2].iter());
         m.insert(3);
-        itertools::assert_equal(m.iter(), vec![9, 7, 3, 3].iter());
+        itertools::assert_equal(m.iter(), [9, 7, 3, 3].iter());
         m.insert(8);
-        itertools::assert_equal(m.iter(), vec![9, 8, 7, 3].iter());
+        itertools::assert_equal(m.iter(), [9, 8, 7, 3].iter());
         m.insert(8);
         m.insert(8);
         m.insert(8);
@@ -70,14 +75,14 @@ mod tests {
         m.insert(8);
         m.insert(8);
         m.insert(7);
-        itertools::assert_equal(m.iter(), vec![9, 8, 8, 8].iter());
+        itertools::assert_equal(m.iter(), [9, 8, 8, 8].iter());
 
         for v in vec![1, 2, 7, 7, 8, 1, 2].into_iter().permutations(7) {
             m = Maximals::new(5);
             for e in v {
                 m.insert(e);
             }
-            itertools::assert_equal(m.iter(), vec![8, 7, 7, 2, 2].iter());
+            itertools::assert_equal(m.iter(), [8, 7, 7, 2, 2].iter());
         }
     }
 
@@ -87,6 +92,7 @@ mod tests {
         data: u8,
     }
 
+    #[allow(clippy::derive_ord_xor_partial_ord)]
     impl Ord for T {
         fn cmp(&self, other: &Self) -> Ordering {
             self.cmp.cmp(&other.cmp)
@@ -96,7 +102,7 @@ mod tests {
     #[test]
     fn maximals_struct() {
         let mut m: Maximals<T> = Maximals::new(4);
-        itertools::assert_equal(m.iter(), vec![].iter());
+        itertools::assert_equal(m.iter(), Vec::<&T>::new());
         let t_1_2 = T { cmp: 1, data: 2 };
         let t_3_1 = T { cmp: 3, data: 1 };
         let t_1_3 = T { cmp: 1, data: 3 };
@@ -104,12 +110,12 @@ mod tests {
         m.insert(t_3_1);
         m.insert(t_1_3);
 
-        itertools::assert_equal(m.iter(), vec![t_3_1, t_1_3, t_1_2].iter());
+        itertools::assert_equal(m.iter(), [t_3_1, t_1_3, t_1_2].iter());
 
         m.clear();
         m.insert(t_3_1);
         m.insert(t_1_3);
         m.insert(t_1_2);
-        itertools::assert_equal(m.iter(), vec![t_3_1, t_1_3, t_1_2].iter());
+        itertools::assert_equal(m.iter(), [t_3_1, t_1_3, t_1_2].iter());
     }
 }
@@ -0,0 +1,149 @@
+use itertools::Itertools;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Formatter;
+use std::rc::Rc;
+use std::time::Duration;
+use txt_timer::maximals::Maximals;
+use txt_timer::stats::Percentiles;
+
+#[derive(Eq, PartialEq, Ord, PartialOrd)]
+struct PairEntry {
+    latency: Duration,
+    begin: Duration,
+    id: String,
+    start: Rc<str>,
+    end: Rc<str>,
+}
+
+impl fmt::Display for PairEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Δ{:.4} @{:.4} {}",
+            self.latency.as_secs_f32(),
+            self.begin.as_secs_f32(),
+            self.id
+        )?;
+        write!(f, "{}{}", self.start, self.end)
+    }
+}
+
+/// Pairs start and end events sharing the same `(?P<id>)` capture and measures their latency
+pub struct Pairs {
+    start: Regex,
+    end: Regex,
+    open: HashMap<String, (Duration, Rc<str>)>,
+    max: Maximals<PairEntry>,
+    latencies: Percentiles,
+    count: usize,
+}
+
+impl Pairs {
+    pub fn new(start: Regex, end: Regex, count: usize) -> Self {
+        Pairs {
+            start,
+            end,
+            open: HashMap::new(),
+            max: Maximals::new(count),
+            latencies: Percentiles::new(),
+            count,
+        }
+    }
+
+    fn id<'a>(regex: &Regex, line: &'a str) -> Option<&'a str> {
+        regex
+            .captures(line)
+            .and_then(|c| c.name("id"))
+            .map(|m| m.as_str())
+    }
+
+    pub fn insert(&mut self, line: &str, total: Duration) {
+        if let Some((id, (begin, start))) =
+            Self::id(&self.end, line).and_then(|id| self.open.remove_entry(id))
+        {
+            let latency = total.saturating_sub(begin);
+            self.latencies.insert(latency);
+            self.max.insert(PairEntry {
+                latency,
+                begin,
+                id,
+                start,
+                end: Rc::from(line),
+            });
+        }
+        if let Some(id) = Self::id(&self.start, line) {
+            self.open.insert(id.to_string(), (total, Rc::from(line)));
+        }
+    }
+}
+
+impl fmt::Display for Pairs {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "matched {} open {}",
+            self.latencies.count(),
+            self.open.len()
+        )?;
+        writeln!(f, "{}", self.latencies)?;
+
+        for e in self.max.iter() {
+            writeln!(f)?;
+            write!(f, "{e}")?;
+        }
+
+        if !self.open.is_empty() {
+            writeln!(f, "\nopen at end:")?;
+        }
+        for (id, (begin, line)) in self
+            .open
+            .iter()
+            .sorted_by_key(|(id, (begin, _))| (*begin, *id))
+            .take(self.count)
+        {
+            write!(f, "@{:.4} {id}\n{line}", begin.as_secs_f32())?;
+        }
+        if self.open.len() > self.count {
+            writeln!(f, "... and {} more", self.open.len() - self.count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pairs::Pairs;
+    use regex::Regex;
+    use std::time::Duration;
+
+    #[test]
+    fn pair_latencies() {
+        let mut pairs = Pairs::new(
+            Regex::new(r"start (?P<id>[a-z]+)").unwrap(),
+            Regex::new(r"end (?P<id>[a-z]+)").unwrap(),
+            1,
+        );
+        pairs.insert("start a\n", Duration::from_millis(0));
+        pairs.insert("start b\n", Duration::from_millis(10));
+        pairs.insert("end a\n", Duration::from_millis(15));
+        pairs.insert("start c\n", Duration::from_millis(20));
+        pairs.insert("end b\n", Duration::from_millis(40));
+        pairs.insert("end x\n", Duration::from_millis(50));
+
+        assert_eq!(
+            pairs.to_string(),
+            "matched 2 open 1\n\
+             mean 0.0225 median 0.0300 p90 0.0300 p99 0.0300 max 0.0300\n\
+             \n\
+             Δ0.0300 @0.0100 b\n\
+             start b\n\
+             end b\n\
+             \n\
+             open at end:\n\
+             @0.0200 c\n\
+             start c\n"
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use crate::color::Palette;
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+use clap::ValueEnum;
+use colored::Colorize;
+use std::io;
+use txt_timer::stats::Score;
+use txt_timer::summary::Position;
+use txt_timer::timer::Stamp;
+
+/// where the time of a line is printed
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum Layout {
+    /// on its own line before the line, or after it with `--attribute before`
+    Separate,
+    /// before the line on the same line, with fixed width fields
+    Inline,
+    /// like `ts` of moreutils, local wall time `%b %d %H:%M:%S` before the line
+    Ts,
+}
+
+/// parts of the time of a line
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum Field {
+    /// delay since the previous line
+    Delta,
+    /// time since the first line
+    Elapsed,
+    /// time of day
+    Wall,
+    /// position in the input
+    Line,
+}
+
+/// Prints the time of lines in the chosen layout
+pub struct Prefix {
+    layout: Layout,
+    fields: Vec<Field>,
+    wall_format: Option<String>,
+    local: bool,
+    anomaly: bool,
+    /// the line is printed after an inline prefix, unless quiet
+    quiet: bool,
+}
+
+impl Prefix {
+    pub fn new(
+        layout: Layout,
+        fields: Vec<Field>,
+        wall_format: Option<String>,
+        local: bool,
+        anomaly: bool,
+        quiet: bool,
+    ) -> Self {
+        let (fields, wall_format, local) = match layout {
+            Layout::Ts => (
+                vec![Field::Wall],
+                wall_format.or_else(|| Some("%b %d %H:%M:%S".to_string())),
+                true,
+            ),
+            _ => (fields, wall_format, local),
+        };
+        Prefix {
+            layout,
+            fields,
+            wall_format,
+            local,
+            anomaly,
+            quiet,
+        }
+    }
+
+    /// wall time, to the millisecond when inline to keep a fixed width
+    fn wall(&self, utc: DateTime<Utc>, inline: bool) -> String {
+        match (&self.wall_format, self.local) {
+            (None, false) if inline => utc.to_rfc3339_opts(SecondsFormat::Millis, true),
+            (None, true) if inline => utc
+                .with_timezone(&Local)
+                .to_rfc3339_opts(SecondsFormat::Millis, false),
+            (None, false) => utc.to_rfc3339(),
+            (None, true) => utc.with_timezone(&Local).to_rfc3339(),
+            (Some(format), false) => utc.format(format).to_string(),
+            (Some(format), true) => utc.with_timezone(&Local).format(format).to_string(),
+        }
+    }
+
+    /// write spaces as wide as the inline fields before a line without a time, keeping the
+    /// lines aligned
+    pub fn write_blank<T: io::Write>(&self, writer: &mut T) -> io::Result<()> {
+        if self.layout == Layout::Separate || self.quiet {
+            return Ok(());
+        }
+        let widths = self.fields.iter().map(|field| match field {
+            Field::Delta if self.anomaly => 18,
+            Field::Delta => 10,
+            Field::Elapsed => 11,
+            Field::Wall => self.wall(DateTime::UNIX_EPOCH, true).chars().count(),
+            Field::Line => 7,
+        });
+        let width = widths.sum::<usize>() + self.fields.len();
+        write!(writer, "{:width$}", "")
+    }
+
+    /// write the fields of `stamp`, colored by `shade`, followed by a new line unless inline
+    pub fn write<T: io::Write>(
+        &self,
+        writer: &mut T,
+        palette: &Palette,
+        stamp: &Stamp,
+        score: Score,
+        shade: f32,
+        position: Position,
+    ) -> io::Result<()> {
+        let inline = self.layout != Layout::Separate;
+        let mut parts = vec![];
+        for field in &self.fields {
+            match field {
+                Field::Delta => {
+                    let x = stamp.last.as_secs_f32();
+                    let delta = if inline {
+                        format!("{x:>9.4}")
+                    } else {
+                        format!("{x:.4}")
+                    };
+                    parts.push(format!("Δ{}", palette.paint(&delta, shade, false)));
+                    if self.anomaly {
+                        let z = if inline {
+                            format!("z{:>6.2}", score.0)
+                        } else {
+                            format!("z{:.2}", score.0)
+                        };
+                        parts.push(palette.paint(&z, shade, false));
+                    }
+                }
+                Field::Elapsed => {
+                    let total = stamp.total.as_secs_f32();
+                    let elapsed = if inline {
+                        format!("{total:>10.4}")
+                    } else {
+                        format!("{total:.4}")
+                    };
+                    parts.push(format!("@{}", elapsed.blue()));
+                }
+                Field::Wall => parts.push(self.wall(stamp.utc, inline).bold().white().to_string()),
+                Field::Line => {
+                    let line = if inline {
+                        format!("{:>7}", position.line)
+                    } else {
+                        position.to_string()
+                    };
+                    parts.push(line.dimmed().to_string());
+                }
+            }
+        }
+        write!(writer, "{}", parts.join(" "))?;
+        if inline && !self.quiet {
+            write!(writer, " ")
+        } else {
+            writeln!(writer)
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use serde_json::json;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use txt_timer::timer::{Stamp, Timer};
+
+fn invalid(e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// arrival time of lines, real time since start or set from a recording while replaying
+#[derive(Clone)]
+pub enum Clock {
+    Real(Instant),
+    Replay(ReplayClock),
+}
+
+impl Clock {
+    pub fn elapsed(&self) -> Duration {
+        match self {
+            Clock::Real(begin) => begin.elapsed(),
+            Clock::Replay(now) => now.get(),
+        }
+    }
+}
+
+/// recorded arrival time of the line being replayed, shared with the timers reading it
+#[derive(Clone, Default)]
+pub struct ReplayClock(Arc<Mutex<Duration>>);
+
+impl ReplayClock {
+    pub fn get(&self) -> Duration {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn set(&self, time: Duration) {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = time;
+    }
+}
+
+/// Timer using the arrival times of a recording, as `ChronoTimer` did when recording it
+pub struct ReplayTimer {
+    clock: ReplayClock,
+    begin: DateTime<Utc>,
+    last: Duration,
+}
+
+impl Timer for ReplayTimer {
+    fn stamp(&mut self, _line: &str) -> Option<Stamp> {
+        // out of order arrivals in the recording are taken as simultaneous
+        let now = self.clock.get().max(self.last);
+        let last = now - self.last;
+        self.last = now;
+        let utc = TimeDelta::from_std(now)
+            .ok()
+            .and_then(|t| self.begin.checked_add_signed(t))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        Some(Stamp {
+            utc,
+            last,
+            total: now,
+        })
+    }
+}
+
+impl ReplayTimer {
+    pub fn new(clock: ReplayClock, begin: DateTime<Utc>) -> Self {
+        ReplayTimer {
+            clock,
+            begin,
+            last: Duration::ZERO,
+        }
+    }
+}
+
+/// Writes lines with their arrival time as an asciinema cast v2 recording
+pub struct Recorder {
+    writer: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, begin: DateTime<Utc>) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let header = json!({
+            "version": 2,
+            "width": 80,
+            "height": 24,
+            "timestamp": begin.timestamp(),
+            "title": "txt-timer",
+        });
+        writeln!(writer, "{header}")?;
+        Ok(Recorder { writer })
+    }
+
+    /// write a line as terminal output, ending with `\r\n` as a terminal would
+    pub fn write(&mut self, time: Duration, line: &str) -> io::Result<()> {
+        let secs = (time.as_secs_f64() * 1e6).round() / 1e6;
+        let data = match line.strip_suffix('\n') {
+            Some(line) => format!("{}\r\n", line.strip_suffix('\r').unwrap_or(line)),
+            None => line.to_string(),
+        };
+        writeln!(self.writer, "{}", json!([secs, "o", data]))
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Reads back the lines of a recording with their arrival time.
+/// Output events of any asciinema cast v2 are split into lines, timed when they are completed,
+/// and the `\r\n` endings of terminal output become `\n`.
+pub struct Recording<R> {
+    events: Lines<R>,
+    pub begin: DateTime<Utc>,
+    partial: String,
+    time: Duration,
+}
+
+impl Recording<BufReader<File>> {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Recording::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: BufRead> Recording<R> {
+    pub fn new(reader: R) -> io::Result<Self> {
+        let mut events = reader.lines();
+        let header = events.next().transpose()?.unwrap_or_default();
+        let header: serde_json::Value = serde_json::from_str(&header).map_err(invalid)?;
+        if header["version"] != 2 {
+            return Err(invalid("not an asciinema cast v2 recording"));
+        }
+        let begin = header["timestamp"]
+            .as_i64()
+            .and_then(|t| DateTime::from_timestamp(t, 0))
+            .unwrap_or_default();
+        Ok(Recording {
+            events,
+            begin,
+            partial: String::new(),
+            time: Duration::ZERO,
+        })
+    }
+
+    fn next_line(&mut self) -> io::Result<Option<(Duration, String)>> {
+        loop {
+            if let Some(end) = self.partial.find('\n') {
+                let rest = self.partial.split_off(end + 1);
+                let mut line = std::mem::replace(&mut self.partial, rest);
+                if line.ends_with("\r\n") {
+                    line.remove(line.len() - 2);
+                }
+                return Ok(Some((self.time, line)));
+            }
+            let Some(event) = self.events.next().transpose()? else {
+                return Ok((!self.partial.is_empty())
+                    .then(|| (self.time, std::mem::take(&mut self.partial))));
+            };
+            if event.trim().is_empty() {
+                continue;
+            }
+            let (time, code, data): (f64, String, String) =
+                serde_json::from_str(&event).map_err(invalid)?;
+            if code == "o" {
+                self.time = Duration::try_from_secs_f64(time).map_err(invalid)?;
+                // the lines are dated from the beginning of the recording
+                TimeDelta::from_std(self.time)
+                    .ok()
+                    .and_then(|t| self.begin.checked_add_signed(t))
+                    .ok_or_else(|| invalid(format!("event time {time} out of range")))?;
+                self.partial.push_str(&data);
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Recording<R> {
+    type Item = io::Result<(Duration, String)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_line().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::record::{Recording, ReplayClock, ReplayTimer};
+    use chrono::DateTime;
+    use std::io;
+    use std::time::Duration;
+    use txt_timer::timer::Timer;
+
+    #[test]
+    fn split_events_into_lines() {
+        let cast = concat!(
+            r#"{"version": 2, "width": 80, "height": 24, "timestamp": 1670833140}"#,
+            "\n",
+            r#"[0.5, "o", "a\r\nb"]"#,
+            "\n",
+            r#"[1.0, "i", "ignored"]"#,
+            "\n",
+            r#"[1.25, "o", "c\nd"]"#,
+            "\n",
+        );
+        let recording = Recording::new(cast.as_bytes()).unwrap();
+        assert_eq!(recording.begin.timestamp(), 1670833140);
+
+        let lines = recording.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(
+            lines,
+            vec![
+                (Duration::from_millis(500), "a\n".to_string()),
+                (Duration::from_millis(1250), "bc\n".to_string()),
+                (Duration::from_millis(1250), "d".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn out_of_range_event() {
+        let cast = concat!(
+            r#"{"version": 2, "width": 80, "height": 24, "timestamp": 1670833140}"#,
+            "\n",
+            r#"[0.5, "o", "a\n"]"#,
+            "\n",
+            r#"[1e15, "o", "b\n"]"#,
+            "\n",
+        );
+        let mut recording = Recording::new(cast.as_bytes()).unwrap();
+        assert_eq!(
+            recording.next().unwrap().unwrap(),
+            (Duration::from_millis(500), "a\n".to_string())
+        );
+        let e = recording.next().unwrap().unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn replay_backwards() {
+        let clock = ReplayClock::default();
+        clock.set(Duration::from_secs(5));
+        let mut timer = ReplayTimer::new(clock.clone(), DateTime::UNIX_EPOCH);
+        timer.stamp("a");
+        clock.set(Duration::from_secs(3));
+        let stamp = timer.stamp("b").unwrap();
+        assert_eq!(stamp.last, Duration::ZERO);
+        assert_eq!(stamp.total, Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,368 @@
+use std::fmt;
+use std::fmt::Formatter;
+use std::time::Duration;
+
+/// Streaming estimate of a single quantile with the P² algorithm (Jain & Chlamtac, 1985),
+/// using constant memory regardless of the number of observations.
+pub struct Quantile {
+    p: f64,
+    count: usize,
+    heights: [f64; 5],
+    positions: [f64; 5],
+    desired: [f64; 5],
+    increments: [f64; 5],
+}
+
+impl Quantile {
+    pub fn new(p: f64) -> Self {
+        Quantile {
+            p,
+            count: 0,
+            heights: [0.0; 5],
+            positions: [1.0, 2.0, 3.0, 4.0, 5.0],
+            desired: [1.0, 1.0 + 2.0 * p, 1.0 + 4.0 * p, 3.0 + 2.0 * p, 5.0],
+            increments: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0],
+        }
+    }
+
+    pub fn insert(&mut self, x: f64) {
+        if self.count < 5 {
+            self.heights[self.count] = x;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_by(f64::total_cmp);
+            }
+            return;
+        }
+        self.count += 1;
+
+        let h = &mut self.heights;
+        let k = if x < h[0] {
+            h[0] = x;
+            0
+        } else if x >= h[4] {
+            h[4] = x;
+            3
+        } else {
+            (1..5).find(|&i| x < h[i]).unwrap_or(4) - 1
+        };
+
+        for n in &mut self.positions[k + 1..] {
+            *n += 1.0;
+        }
+        for (d, i) in self.desired.iter_mut().zip(self.increments) {
+            *d += i;
+        }
+
+        for i in 1..4 {
+            let d = self.desired[i] - self.positions[i];
+            let n = &self.positions;
+            if (d >= 1.0 && n[i + 1] - n[i] > 1.0) || (d <= -1.0 && n[i - 1] - n[i] < -1.0) {
+                let d = d.signum();
+                let parabolic = self.parabolic(i, d);
+                self.heights[i] =
+                    if self.heights[i - 1] < parabolic && parabolic < self.heights[i + 1] {
+                        parabolic
+                    } else {
+                        self.linear(i, d)
+                    };
+                self.positions[i] += d;
+            }
+        }
+    }
+
+    fn parabolic(&self, i: usize, d: f64) -> f64 {
+        let (q, n) = (&self.heights, &self.positions);
+        q[i] + d / (n[i + 1] - n[i - 1])
+            * ((n[i] - n[i - 1] + d) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                + (n[i + 1] - n[i] - d) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]))
+    }
+
+    fn linear(&self, i: usize, d: f64) -> f64 {
+        let (q, n) = (&self.heights, &self.positions);
+        let j = if d > 0.0 { i + 1 } else { i - 1 };
+        q[i] + d * (q[j] - q[i]) / (n[j] - n[i])
+    }
+
+    pub fn estimate(&self) -> f64 {
+        if self.count >= 5 {
+            return self.heights[2];
+        }
+        let mut seen = self.heights[..self.count].to_vec();
+        seen.sort_by(f64::total_cmp);
+        let idx = ((seen.len() as f64 - 1.0) * self.p).round() as usize;
+        seen.get(idx).copied().unwrap_or(0.0)
+    }
+}
+
+/// Summary of a stream of durations in constant memory
+pub struct Percentiles {
+    count: usize,
+    sum: Duration,
+    max: Duration,
+    median: Quantile,
+    p90: Quantile,
+    p99: Quantile,
+}
+
+impl Default for Percentiles {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Percentiles {
+    pub fn new() -> Self {
+        Percentiles {
+            count: 0,
+            sum: Duration::ZERO,
+            max: Duration::ZERO,
+            median: Quantile::new(0.5),
+            p90: Quantile::new(0.9),
+            p99: Quantile::new(0.99),
+        }
+    }
+
+    pub fn insert(&mut self, d: Duration) {
+        self.count += 1;
+        self.sum += d;
+        self.max = self.max.max(d);
+
+        let x = d.as_secs_f64();
+        self.median.insert(x);
+        self.p90.insert(x);
+        self.p99.insert(x);
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    fn mean(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.sum.as_secs_f64() / self.count as f64
+        }
+    }
+}
+
+impl fmt::Display for Percentiles {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "mean {:.4} median {:.4} p90 {:.4} p99 {:.4} max {:.4}",
+            self.mean(),
+            self.median.estimate(),
+            self.p90.estimate(),
+            self.p99.estimate(),
+            self.max.as_secs_f64()
+        )
+    }
+}
+
+/// upper bounds of the histogram buckets, the last bucket has no bound
+const BUCKETS: [Duration; 5] = [
+    Duration::from_millis(1),
+    Duration::from_millis(10),
+    Duration::from_millis(100),
+    Duration::from_secs(1),
+    Duration::from_secs(10),
+];
+
+const BAR_WIDTH: usize = 40;
+
+pub struct DelayStats {
+    color_range: Duration,
+    total: Duration,
+    delays: Percentiles,
+    histogram: [usize; BUCKETS.len() + 1],
+    over_range: Duration,
+    over_range_count: usize,
+}
+
+impl DelayStats {
+    pub fn new(color_range: Duration) -> Self {
+        DelayStats {
+            color_range,
+            total: Duration::ZERO,
+            delays: Percentiles::new(),
+            histogram: [0; BUCKETS.len() + 1],
+            over_range: Duration::ZERO,
+            over_range_count: 0,
+        }
+    }
+
+    pub fn insert(&mut self, last: Duration, total: Duration) {
+        self.total = self.total.max(total);
+        self.delays.insert(last);
+        self.histogram[BUCKETS.partition_point(|b| *b <= last)] += 1;
+
+        if last > self.color_range {
+            self.over_range += last;
+            self.over_range_count += 1;
+        }
+    }
+}
+
+fn bucket_label(i: usize) -> String {
+    match BUCKETS.get(i) {
+        Some(b) => format!("<{b:?}"),
+        None => format!(">={:?}", BUCKETS[i - 1]),
+    }
+}
+
+impl fmt::Display for DelayStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "lines {} total {:.4}",
+            self.delays.count,
+            self.total.as_secs_f64()
+        )?;
+        writeln!(f, "{}", self.delays)?;
+
+        let highest = self.histogram.iter().copied().max().unwrap_or(0).max(1);
+        for (i, c) in self.histogram.iter().enumerate() {
+            write!(f, "{:>7} {c:>8}", bucket_label(i))?;
+            if *c > 0 {
+                write!(f, " {}", "#".repeat((c * BAR_WIDTH).div_ceil(highest)))?;
+            }
+            writeln!(f)?;
+        }
+
+        let share = if self.total.is_zero() {
+            0.0
+        } else {
+            100.0 * self.over_range.as_secs_f64() / self.total.as_secs_f64()
+        };
+        writeln!(
+            f,
+            "over {:.4}: {:.4} in {} gaps ({share:.1}% of total)",
+            self.color_range.as_secs_f64(),
+            self.over_range.as_secs_f64(),
+            self.over_range_count
+        )
+    }
+}
+
+/// how anomalous a delay is, in standard deviations from the moving mean
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Score(pub f32);
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Exponentially weighted moving mean and variance of delays, scoring each new delay by its
+/// z-score against the delays before it. The first `1 / alpha` delays only warm it up.
+pub struct Ewma {
+    alpha: f64,
+    mean: f64,
+    variance: f64,
+    count: usize,
+}
+
+impl Ewma {
+    /// deviations smaller than this do not make delays anomalous, when lines come at a steady pace
+    const MIN_DEVIATION: f64 = 0.001;
+
+    pub fn new(alpha: f64) -> Self {
+        Ewma {
+            alpha,
+            mean: 0.0,
+            variance: 0.0,
+            count: 0,
+        }
+    }
+
+    pub fn insert(&mut self, delay: Duration) -> Score {
+        let x = delay.as_secs_f64();
+        if self.count == 0 {
+            self.mean = x;
+        }
+        self.count += 1;
+
+        let diff = x - self.mean;
+        let score = if self.count as f64 > 1.0 / self.alpha {
+            diff / self.variance.sqrt().max(Self::MIN_DEVIATION)
+        } else {
+            0.0
+        };
+        let increment = self.alpha * diff;
+        self.mean += increment;
+        self.variance = (1.0 - self.alpha) * (self.variance + diff * increment);
+        Score(score as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stats::{DelayStats, Ewma, Quantile};
+    use std::time::Duration;
+
+    #[test]
+    fn ewma_scores() {
+        let mut e = Ewma::new(0.5);
+        let scores =
+            [100, 100, 110, 100, 1000, 100].map(|ms| e.insert(Duration::from_millis(ms)).0);
+        // warming up
+        assert_eq!(scores[0], 0.0);
+        assert_eq!(scores[1], 0.0);
+        assert!((scores[2] - 10.0).abs() < 1e-3);
+        assert!(scores[4] > 100.0);
+        assert!(scores[5] < 0.0);
+    }
+
+    #[test]
+    fn quantile_estimates() {
+        let mut median = Quantile::new(0.5);
+        let mut p90 = Quantile::new(0.9);
+        assert_eq!(median.estimate(), 0.0);
+
+        for i in 0..10_000u64 {
+            let x = ((i * 7919) % 10_000) as f64;
+            median.insert(x);
+            p90.insert(x);
+        }
+        assert!((median.estimate() - 5_000.0).abs() < 100.0);
+        assert!((p90.estimate() - 9_000.0).abs() < 100.0);
+
+        let mut few = Quantile::new(0.5);
+        few.insert(3.0);
+        few.insert(1.0);
+        few.insert(2.0);
+        assert_eq!(few.estimate(), 2.0);
+    }
+
+    #[test]
+    fn delay_stats() {
+        let mut stats = DelayStats::new(Duration::from_millis(200));
+        stats.insert(Duration::ZERO, Duration::ZERO);
+        stats.insert(Duration::from_millis(50), Duration::from_millis(50));
+        stats.insert(Duration::from_millis(500), Duration::from_millis(550));
+        stats.insert(Duration::from_secs(20), Duration::from_millis(20_550));
+
+        assert_eq!(stats.delays.count, 4);
+        assert_eq!(stats.delays.max, Duration::from_secs(20));
+        assert_eq!(stats.histogram, [1, 0, 1, 1, 0, 1]);
+        assert_eq!(stats.over_range, Duration::from_millis(20_500));
+        assert_eq!(stats.over_range_count, 2);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::analyzer::Analyzer;
+use crate::timer::{Stamp, Timer};
+use std::future::{poll_fn, Future};
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::AsyncBufRead;
+use tokio_stream::{Stream, StreamExt};
+
+/// Lines of an async reader with their line endings, decoded lossily when they are not UTF-8 as
+/// the command does
+pub struct AsyncReadLines<R> {
+    reader: R,
+    /// start of a line whose end is not read yet
+    buffer: Vec<u8>,
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncReadLines<R> {
+    /// next line as read, before decoding
+    fn poll_next_bytes(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<Vec<u8>>>> {
+        loop {
+            let available = match ready!(Pin::new(&mut self.reader).poll_fill_buf(cx)) {
+                Ok(available) => available,
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            };
+            if available.is_empty() {
+                let line = std::mem::take(&mut self.buffer);
+                return Poll::Ready((!line.is_empty()).then_some(Ok(line)));
+            }
+            match available.iter().position(|&b| b == b'\n') {
+                Some(end) => {
+                    self.buffer.extend_from_slice(&available[..=end]);
+                    Pin::new(&mut self.reader).consume(end + 1);
+                    return Poll::Ready(Some(Ok(std::mem::take(&mut self.buffer))));
+                }
+                None => {
+                    let read = available.len();
+                    self.buffer.extend_from_slice(available);
+                    Pin::new(&mut self.reader).consume(read);
+                }
+            }
+        }
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> Stream for AsyncReadLines<R> {
+    type Item = io::Result<String>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let line = ready!(self.poll_next_bytes(cx));
+        Poll::Ready(line.map(|line| line.map(|line| String::from_utf8_lossy(&line).into_owned())))
+    }
+}
+
+/// Timing of lines from any stream, without a thread blocking on them
+pub trait TimeStream: Stream<Item = String> + Sized {
+    /// lines with their stamps, skipping the ones `timer` finds no time in
+    fn stamped<T: Timer>(self, mut timer: T) -> impl Stream<Item = (String, Stamp)> {
+        self.filter_map(move |line| {
+            let stamp = timer.stamp(&line)?;
+            Some((line, stamp))
+        })
+    }
+
+    /// analyze every line, given a line ending if it has none, and return `analyzer` once the
+    /// stream ended
+    fn analyze(self, mut analyzer: Analyzer) -> impl Future<Output = Analyzer> {
+        async move {
+            let mut lines = std::pin::pin!(self);
+            while let Some(mut line) = lines.next().await {
+                if !line.ends_with('\n') {
+                    line.push('\n');
+                }
+                analyzer.insert(&line);
+            }
+            analyzer.finish();
+            analyzer
+        }
+    }
+}
+
+impl<S: Stream<Item = String>> TimeStream for S {}
+
+/// Timing of the lines of an async reader, such as the output of a child process
+pub trait AsyncTimeReader: AsyncBufRead + Unpin + Sized {
+    /// lines with their line endings
+    fn read_lines(self) -> AsyncReadLines<Self> {
+        AsyncReadLines {
+            reader: self,
+            buffer: vec![],
+        }
+    }
+
+    /// lines with their line endings and their stamps, skipping the ones `timer` finds no time in
+    fn stamped_lines<T: Timer>(
+        self,
+        mut timer: T,
+    ) -> impl Stream<Item = io::Result<(String, Stamp)>> {
+        self.read_lines().filter_map(move |line| match line {
+            Ok(line) => timer.stamp(&line).map(|stamp| Ok((line, stamp))),
+            Err(e) => Some(Err(e)),
+        })
+    }
+
+    /// analyze every line until the end of the input, and return `analyzer`
+    fn analyze(self, mut analyzer: Analyzer) -> impl Future<Output = io::Result<Analyzer>> {
+        async move {
+            let mut lines = self.read_lines();
+            while let Some(line) = poll_fn(|cx| lines.poll_next_bytes(cx)).await {
+                let line = line?;
+                analyzer.insert_decoded(&String::from_utf8_lossy(&line), line.len());
+            }
+            analyzer.finish();
+            Ok(analyzer)
+        }
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncTimeReader for R {}
+
+#[cfg(test)]
+mod tests {
+    use crate::analyzer::{Analyzer, AnalyzerOptions};
+    use crate::stream::{AsyncTimeReader, TimeStream};
+    use crate::timer::RegexTimer;
+    use regex::Regex;
+    use std::time::Duration;
+    use tokio_stream::StreamExt;
+
+    fn timer() -> RegexTimer {
+        RegexTimer::new(Regex::new(r"^(?P<time>\d+)").unwrap(), "%s")
+    }
+
+    #[tokio::test]
+    async fn stamped() {
+        let lines = tokio_stream::iter(["10 a", "b", "12 c"].map(String::from));
+        let delays = lines
+            .stamped(timer())
+            .map(|(_, stamp)| stamp.last)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(delays, [Duration::ZERO, Duration::from_secs(2)]);
+
+        let input = "10 a\n11 b\r\n15 c".as_bytes();
+        let lines = input
+            .stamped_lines(timer())
+            .map(|line| line.unwrap().0)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(lines, ["10 a\n", "11 b\r\n", "15 c"]);
+
+        let input: &[u8] = b"10 a\xff\n11 b\n";
+        let lines = input
+            .stamped_lines(timer())
+            .map(|line| line.unwrap().0)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(lines, ["10 a\u{fffd}\n", "11 b\n"]);
+    }
+
+    #[tokio::test]
+    async fn analyze() {
+        let analyzer = || Analyzer::new(Box::new(timer()), AnalyzerOptions::default());
+
+        let lines = tokio_stream::iter(["10 a", "11 b", "15 c"].map(String::from));
+        let from_lines = lines.analyze(analyzer()).await;
+        let input = "10 a\n11 b\n15 c\n".as_bytes();
+        let from_reader = input.analyze(analyzer()).await.unwrap();
+        for a in [from_lines, from_reader] {
+            let summary = a.maximals().entries().map(|e| e.line()).collect::<Vec<_>>();
+            assert_eq!(summary, ["15 c\n", "11 b\n", "10 a\n"]);
+        }
+
+        // offsets are those of the input, not of the decoded lines
+        let input = b"10 a\xff\n11 b\n".as_slice();
+        let a = input.analyze(analyzer()).await.unwrap();
+        let first = a.maximals().entries().next().unwrap();
+        assert_eq!((first.line(), first.position.offset), ("11 b\n", 6));
+    }
+
+    #[test]
+    fn futures_are_send() {
+        fn assert_send<T: Send>(_: &T) {}
+        let analyzer = || Analyzer::new(Box::new(timer()), AnalyzerOptions::default());
+        let lines = tokio_stream::iter(["10 a"].map(String::from));
+        assert_send(&lines.analyze(analyzer()));
+        assert_send(&"10 a\n".as_bytes().analyze(analyzer()));
+        assert_send(&"10 a\n".as_bytes().stamped_lines(timer()));
+    }
+}
@@ -0,0 +1,239 @@
+use crate::maximals::Maximals;
+use crate::stats::Score;
+use crate::timer::Stamp;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::fmt::Formatter;
+use std::sync::Arc;
+
+/// location of a line in the input
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Default)]
+pub struct Position {
+    /// 1-based line number
+    pub line: usize,
+    /// byte offset of the start of the line
+    pub offset: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, byte {}", self.line, self.offset)
+    }
+}
+
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone)]
+struct ContextLine {
+    number: usize,
+    text: Arc<str>,
+}
+
+/// how the maximals are printed
+#[derive(Clone, Copy, Default)]
+pub struct SummaryOptions {
+    /// print overlapping contexts once
+    pub merge_contexts: bool,
+    /// prefix lines with their number
+    pub line_number: bool,
+    /// print the z-score of delays
+    pub anomaly: bool,
+}
+
+#[derive(Eq, PartialEq, Ord, PartialOrd)]
+pub struct MaximalsStampsEntry {
+    /// ranks first, all zero unless ranking by anomaly
+    pub score: Score,
+    pub stamp: Stamp,
+    pub position: Position,
+    index: usize,
+    /// number of lines in `lines` preceding the one at `index`
+    before: usize,
+    lines: Vec<ContextLine>,
+}
+
+impl MaximalsStampsEntry {
+    fn first_index(&self) -> usize {
+        self.index - self.before
+    }
+
+    fn last_index(&self) -> usize {
+        self.first_index() + self.lines.len().saturating_sub(1)
+    }
+
+    /// the line the delay is attributed to
+    pub fn line(&self) -> &str {
+        &self.lines[self.before].text
+    }
+
+    fn write_header(&self, f: &mut Formatter<'_>, options: SummaryOptions) -> fmt::Result {
+        write!(
+            f,
+            "Δ{:.4} @{:.4}",
+            self.stamp.last.as_secs_f32(),
+            self.stamp.total.as_secs_f32()
+        )?;
+        if options.anomaly {
+            write!(f, " z{:.2}", self.score.0)?;
+        }
+        if options.line_number {
+            write!(f, " {}", self.position)?;
+        }
+        writeln!(f)
+    }
+
+    fn write(&self, f: &mut Formatter<'_>, options: SummaryOptions) -> fmt::Result {
+        self.write_header(f, options)?;
+
+        for l in &self.lines {
+            write_context_line(f, l, l.number == self.position.line, options)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_context_line(
+    f: &mut Formatter<'_>,
+    l: &ContextLine,
+    matched: bool,
+    options: SummaryOptions,
+) -> fmt::Result {
+    if !options.line_number {
+        return write!(f, "{}", l.text);
+    }
+    for (i, text) in l.text.split_inclusive('\n').enumerate() {
+        let separator = if matched && i == 0 { ':' } else { '-' };
+        write!(f, "{}{separator}{text}", l.number + i)?;
+    }
+    Ok(())
+}
+
+/// Largest delays with the lines around them
+pub struct MaximalsStampsBuffer {
+    max: Maximals<MaximalsStampsEntry>,
+    lines: VecDeque<ContextLine>,
+    lines_before: usize,
+    lines_after: usize,
+    /// index of recently inserted maximals still collecting lines after, with remaining count
+    pending: VecDeque<(usize, usize)>,
+    inserted: usize,
+    options: SummaryOptions,
+}
+
+impl MaximalsStampsBuffer {
+    pub fn new(count: usize, before: usize, after: usize, options: SummaryOptions) -> Self {
+        MaximalsStampsBuffer {
+            max: Maximals::new(count),
+            lines: VecDeque::with_capacity(before + 1),
+            lines_before: before,
+            lines_after: after,
+            pending: VecDeque::with_capacity(after),
+            inserted: 0,
+            options,
+        }
+    }
+
+    pub fn insert(&mut self, stamp: Stamp, score: Score, position: Position, value: &str) {
+        let line = ContextLine {
+            number: position.line,
+            text: Arc::from(value),
+        };
+        let index = self.inserted;
+        self.inserted += 1;
+
+        self.extend_pending(&line);
+
+        self.lines.push_back(line);
+        if self.lines.len() > self.lines_before + 1 {
+            self.lines.pop_front();
+        }
+
+        if let Some(b) = self.max.insert(MaximalsStampsEntry {
+            score,
+            stamp,
+            position,
+            index,
+            before: self.lines.len() - 1,
+            lines: vec![],
+        }) {
+            b.lines.extend(self.lines.iter().cloned());
+            if self.lines_after > 0 {
+                self.pending.push_back((index, self.lines_after));
+            }
+        };
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &MaximalsStampsEntry> + '_ {
+        self.max.iter()
+    }
+
+    fn extend_pending(&mut self, line: &ContextLine) {
+        for (index, remaining) in self.pending.iter_mut() {
+            if let Some(e) = self.max.iter_mut().find(|e| e.index == *index) {
+                e.lines.push(line.clone());
+            }
+            *remaining -= 1;
+        }
+        self.pending.retain(|(_, remaining)| *remaining > 0);
+    }
+
+    fn fmt_merged(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (rank, e) in self.max.iter().enumerate() {
+            write!(f, "#{} ", rank + 1)?;
+            e.write_header(f, self.options)?;
+        }
+        writeln!(f)?;
+
+        let mut by_position = self.max.iter().enumerate().collect::<Vec<_>>();
+        by_position.sort_by_key(|(_, e)| e.index);
+
+        let mut block: BTreeMap<usize, &ContextLine> = BTreeMap::new();
+        let mut marks: BTreeMap<usize, (usize, &MaximalsStampsEntry)> = BTreeMap::new();
+        for (rank, e) in by_position {
+            if block
+                .last_key_value()
+                .is_some_and(|(end, _)| e.first_index() > end + 1)
+            {
+                self.write_block(f, &block, &marks)?;
+                block.clear();
+                marks.clear();
+            }
+            block.extend((e.first_index()..=e.last_index()).zip(&e.lines));
+            marks.insert(e.index, (rank, e));
+        }
+        if !block.is_empty() {
+            self.write_block(f, &block, &marks)?;
+        }
+        Ok(())
+    }
+
+    fn write_block(
+        &self,
+        f: &mut Formatter<'_>,
+        block: &BTreeMap<usize, &ContextLine>,
+        marks: &BTreeMap<usize, (usize, &MaximalsStampsEntry)>,
+    ) -> fmt::Result {
+        for (index, l) in block {
+            let mark = marks.get(index);
+            if let Some((rank, e)) = mark {
+                write!(f, "#{} ", rank + 1)?;
+                e.write_header(f, self.options)?;
+            }
+            write_context_line(f, l, mark.is_some(), self.options)?;
+        }
+        writeln!(f)?;
+        writeln!(f)
+    }
+}
+
+impl fmt::Display for MaximalsStampsBuffer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.options.merge_contexts {
+            return self.fmt_merged(f);
+        }
+        for e in self.max.iter() {
+            e.write(f, self.options)?;
+            writeln!(f)?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
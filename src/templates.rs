@@ -0,0 +1,156 @@
+use itertools::Itertools;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Formatter;
+use std::time::Duration;
+
+/// Reduces lines to templates by masking the parts that usually vary between
+/// occurrences of the same message: quoted strings, UUIDs, hex values and numbers.
+pub struct Normalizer {
+    regex: Regex,
+}
+
+impl Normalizer {
+    pub fn new() -> Self {
+        let regex = Regex::new(concat!(
+            r#"(?P<str>"[^"]*"|'[^']*')"#,
+            r"|(?P<uuid>[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12})",
+            r"|(?P<hex>0[xX][0-9a-fA-F]+|[0-9a-f]{8,})",
+            r"|(?P<num>[0-9]+(\.[0-9]+)?)",
+        ))
+        .unwrap();
+        Normalizer { regex }
+    }
+
+    pub fn normalize(&self, line: &str) -> String {
+        self.regex
+            .replace_all(line.trim_end(), |c: &Captures| {
+                match ["str", "uuid", "hex", "num"]
+                    .into_iter()
+                    .find_map(|n| Some(n).zip(c.name(n)))
+                {
+                    // long runs of digits only are numbers, not hex
+                    Some(("hex", m)) if m.as_str().bytes().all(|b| b.is_ascii_digit()) => "<num>",
+                    Some(("str", _)) => "<str>",
+                    Some(("uuid", _)) => "<uuid>",
+                    Some(("hex", _)) => "<hex>",
+                    _ => "<num>",
+                }
+            })
+            .into_owned()
+    }
+}
+
+#[derive(Default)]
+struct TemplateTotal {
+    total: Duration,
+    count: usize,
+}
+
+/// Accumulates the delay attributed to each template
+pub struct Templates {
+    normalizer: Normalizer,
+    totals: HashMap<String, TemplateTotal>,
+    count: usize,
+}
+
+impl Templates {
+    pub fn new(count: usize) -> Self {
+        Templates {
+            normalizer: Normalizer::new(),
+            totals: HashMap::new(),
+            count,
+        }
+    }
+
+    pub fn insert(&mut self, line: &str, last: Duration) {
+        let t = self
+            .totals
+            .entry(self.normalizer.normalize(line))
+            .or_default();
+        t.total += last;
+        t.count += 1;
+    }
+
+    /// templates with their total delay and count, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Duration, usize)> {
+        self.totals
+            .iter()
+            .map(|(template, t)| (template.as_str(), t.total, t.count))
+    }
+
+    pub fn get(&self, template: &str) -> Option<Duration> {
+        self.totals.get(template).map(|t| t.total)
+    }
+
+    fn write_top<K: Ord>(
+        &self,
+        f: &mut Formatter<'_>,
+        key: impl Fn(&TemplateTotal) -> K,
+    ) -> fmt::Result {
+        for (template, t) in self
+            .totals
+            .iter()
+            .sorted_by(|(a_name, a), (b_name, b)| key(b).cmp(&key(a)).then(a_name.cmp(b_name)))
+            .take(self.count)
+        {
+            writeln!(
+                f,
+                "{:>12.4} {:>8} {template}",
+                t.total.as_secs_f32(),
+                t.count
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Templates {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "by total time:")?;
+        self.write_top(f, |t| t.total)?;
+        writeln!(f, "by count:")?;
+        self.write_top(f, |t| t.count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::templates::{Normalizer, Templates};
+    use std::time::Duration;
+
+    #[test]
+    fn normalize() {
+        let n = Normalizer::new();
+        assert_eq!(
+            n.normalize("processing item 123 of 4.5\n"),
+            "processing item <num> of <num>"
+        );
+        assert_eq!(n.normalize("id 123456789"), "id <num>");
+        assert_eq!(
+            n.normalize(
+                "job 123e4567-e89b-12d3-a456-426614174000 at 0x7ffd commit 9fceb02d0ae598e9"
+            ),
+            "job <uuid> at <hex> commit <hex>"
+        );
+        assert_eq!(
+            n.normalize(r#"open "/tmp/a 1" and 'b'"#),
+            "open <str> and <str>"
+        );
+    }
+
+    #[test]
+    fn totals() {
+        let mut t = Templates::new(1);
+        t.insert("item 1", Duration::from_millis(10));
+        t.insert("item 2", Duration::from_millis(10));
+        t.insert("item 3", Duration::from_millis(10));
+        t.insert("slow", Duration::from_millis(20));
+
+        assert_eq!(
+            t.to_string(),
+            "by total time:\n      0.0300        3 item <num>\nby count:\n      0.0300        3 item <num>\n"
+        );
+    }
+}
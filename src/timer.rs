@@ -2,11 +2,17 @@ use chrono::{DateTime, NaiveDateTime, Utc};
 use regex::Regex;
 use std::time::{Duration, Instant};
 
-pub trait Timer {
+pub trait Timer: Send {
     fn stamp(&mut self, line: &str) -> Option<Stamp>;
 }
 
-#[derive(Eq, PartialEq, Ord, PartialOrd)]
+impl<T: Timer + ?Sized> Timer for Box<T> {
+    fn stamp(&mut self, line: &str) -> Option<Stamp> {
+        (**self).stamp(line)
+    }
+}
+
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Copy)]
 pub struct Stamp {
     pub last: Duration,
     pub total: Duration,
@@ -29,6 +35,12 @@ impl Timer for ChronoTimer {
     }
 }
 
+impl Default for ChronoTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ChronoTimer {
     pub fn new() -> Self {
         let now = Instant::now();
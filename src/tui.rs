@@ -0,0 +1,246 @@
+use crate::color::delay_rgb;
+use crate::Handler;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, Paragraph, Sparkline};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::VecDeque;
+use std::io;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// refresh interval of the dashboard
+const FRAME: Duration = Duration::from_millis(100);
+/// lines kept for the scrolling log
+const SCROLLBACK: usize = 1000;
+/// seconds of history kept for the rate sparkline
+const RATE_HISTORY: usize = 512;
+
+fn quit_requested() -> io::Result<bool> {
+    while event::poll(Duration::ZERO)? {
+        if let Event::Key(key) = event::read()? {
+            let quit = match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => true,
+                KeyCode::Char('c') => key.modifiers.contains(KeyModifiers::CONTROL),
+                _ => false,
+            };
+            if quit && key.kind == KeyEventKind::Press {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+struct Dashboard {
+    handler: Handler,
+    log: VecDeque<(Option<Duration>, String)>,
+    /// lines received per second, the last one is the current second
+    rates: VecDeque<u64>,
+    begin: Instant,
+    second: Instant,
+    lines: usize,
+    ended: bool,
+}
+
+/// Full screen view of the input with delays, top maximals and line rate, until the user quits.
+/// Returns the handler to print the summary after the terminal is restored.
+pub fn run(
+    handler: Handler,
+    lines: Receiver<io::Result<Vec<u8>>>,
+    term: &AtomicBool,
+) -> io::Result<Handler> {
+    // the dashboard is drawn on standard output, it must not end up in a redirection
+    if !io::stdout().is_terminal() {
+        return Err(io::Error::other(
+            "--tui needs a terminal: standard output is not a terminal",
+        ));
+    }
+    let mut terminal = ratatui::try_init()
+        .map_err(|e| io::Error::new(e.kind(), format!("--tui needs a terminal: {e}")))?;
+    let now = Instant::now();
+    let mut dashboard = Dashboard {
+        handler,
+        log: VecDeque::with_capacity(SCROLLBACK),
+        rates: VecDeque::from([0]),
+        begin: now,
+        second: now,
+        lines: 0,
+        ended: false,
+    };
+    let rv = dashboard.run(&mut terminal, &lines, term);
+    ratatui::restore();
+    rv.map(|_| dashboard.handler)
+}
+
+impl Dashboard {
+    fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        lines: &Receiver<io::Result<Vec<u8>>>,
+        term: &AtomicBool,
+    ) -> io::Result<()> {
+        let mut next_frame = Instant::now();
+        while !term.load(Ordering::Relaxed) {
+            if Instant::now() >= next_frame {
+                self.tick();
+                terminal.draw(|frame| self.draw(frame))?;
+                if quit_requested()? {
+                    break;
+                }
+                next_frame = Instant::now() + FRAME;
+            }
+
+            // lines are processed as soon as they arrive, to time them precisely
+            let timeout = next_frame.saturating_duration_since(Instant::now());
+            if self.ended {
+                event::poll(timeout)?;
+                continue;
+            }
+            match lines.recv_timeout(timeout) {
+                Ok(line) => self.receive(&line?)?,
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => self.ended = true,
+            }
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self, line: &[u8]) -> io::Result<()> {
+        let stamp = self.handler.process_line(line, &mut io::sink())?;
+        let line = String::from_utf8_lossy(line);
+
+        if self.log.len() == SCROLLBACK {
+            self.log.pop_front();
+        }
+        self.log
+            .push_back((stamp.map(|s| s.last), line.trim_end().to_string()));
+        self.lines += 1;
+        if let Some(current) = self.rates.back_mut() {
+            *current += 1;
+        }
+        Ok(())
+    }
+
+    fn tick(&mut self) {
+        while self.second.elapsed() >= Duration::from_secs(1) {
+            self.second += Duration::from_secs(1);
+            if self.rates.len() == RATE_HISTORY {
+                self.rates.pop_front();
+            }
+            self.rates.push_back(0);
+        }
+    }
+
+    fn delay_span(&self, delay: Option<Duration>) -> [Span<'static>; 2] {
+        match delay {
+            Some(d) => {
+                let (r, g, b) = delay_rgb(d.as_secs_f32(), self.handler.cli.color_range);
+                let color = Color::Rgb(r, g, b);
+                [
+                    Span::styled("█", Style::new().fg(color)),
+                    Span::styled(format!(" Δ{:.4} ", d.as_secs_f32()), Style::new().fg(color)),
+                ]
+            }
+            None => [Span::raw(" "), Span::raw(" ".repeat(9))],
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(5),
+        ])
+        .areas(frame.area());
+        let [log, maximals] =
+            Layout::horizontal([Constraint::Percentage(65), Constraint::Percentage(35)])
+                .areas(body);
+
+        self.draw_header(frame, header);
+        self.draw_log(frame, log);
+        self.draw_maximals(frame, maximals);
+        self.draw_rate(frame, footer);
+    }
+
+    fn draw_header(&self, frame: &mut Frame, area: Rect) {
+        let elapsed = self.begin.elapsed().as_secs();
+        let status = if self.ended { "input ended" } else { "reading" };
+        let text = format!(
+            " txt-timer  elapsed {:02}:{:02}:{:02}  lines {}  {status}  (q to quit)",
+            elapsed / 3600,
+            elapsed / 60 % 60,
+            elapsed % 60,
+            self.lines
+        );
+        frame.render_widget(Paragraph::new(text).reversed(), area);
+    }
+
+    fn draw_log(&self, frame: &mut Frame, area: Rect) {
+        let height = area.height.saturating_sub(2) as usize;
+        let lines = self
+            .log
+            .iter()
+            .skip(self.log.len().saturating_sub(height))
+            .map(|(delay, text)| {
+                let mut spans = self.delay_span(*delay).to_vec();
+                spans.push(Span::raw(text.as_str()));
+                Line::from(spans)
+            })
+            .collect::<Vec<_>>();
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("log")),
+            area,
+        );
+    }
+
+    fn draw_maximals(&self, frame: &mut Frame, area: Rect) {
+        let items = self
+            .handler
+            .analyzer
+            .maximals()
+            .entries()
+            .map(|e| {
+                let mut header = self.delay_span(Some(e.stamp.last)).to_vec();
+                header.push(Span::styled(
+                    format!(
+                        "@{:.4} line {}",
+                        e.stamp.total.as_secs_f32(),
+                        e.position.line
+                    ),
+                    Style::new().blue(),
+                ));
+                ListItem::new(vec![
+                    Line::from(header),
+                    Line::raw(format!("  {}", e.line().trim_end())),
+                ])
+            })
+            .collect::<Vec<_>>();
+        frame.render_widget(
+            List::new(items).block(Block::bordered().title("maximals")),
+            area,
+        );
+    }
+
+    fn draw_rate(&self, frame: &mut Frame, area: Rect) {
+        let width = area.width.saturating_sub(2) as usize;
+        let data = self
+            .rates
+            .iter()
+            .skip(self.rates.len().saturating_sub(width))
+            .copied()
+            .collect::<Vec<_>>();
+        let previous = self.rates.iter().rev().nth(1).copied().unwrap_or(0);
+        frame.render_widget(
+            Sparkline::default()
+                .data(&data)
+                .style(Style::new().cyan())
+                .block(Block::bordered().title(format!("lines/s ({previous} last second)"))),
+            area,
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fmt::Formatter;
+use std::rc::Rc;
+use std::time::Duration;
+use txt_timer::maximals::Maximals;
+use txt_timer::timer::Stamp;
+
+#[derive(Eq, PartialEq, Ord, PartialOrd)]
+struct WindowEntry {
+    last: Duration,
+    total: Duration,
+    line: usize,
+    text: Rc<str>,
+}
+
+struct Window {
+    start: DateTime<Utc>,
+    lines: usize,
+    max: Maximals<WindowEntry>,
+}
+
+/// Maximals of consecutive time windows aligned on the time of the lines, keeping the last ones
+pub struct Windows {
+    width: TimeDelta,
+    keep: usize,
+    count: usize,
+    windows: VecDeque<Window>,
+}
+
+impl Windows {
+    pub fn new(width: Duration, keep: usize, count: usize) -> Self {
+        Windows {
+            width: TimeDelta::from_std(width).unwrap_or(TimeDelta::MAX),
+            keep,
+            count,
+            windows: VecDeque::with_capacity(keep),
+        }
+    }
+
+    fn window_start(&self, utc: DateTime<Utc>) -> DateTime<Utc> {
+        let width = self.width.num_milliseconds().max(1);
+        let millis = utc.timestamp_millis().div_euclid(width) * width;
+        DateTime::from_timestamp_millis(millis).unwrap_or(utc)
+    }
+
+    pub fn insert(&mut self, stamp: &Stamp, line: usize, text: &str) {
+        let start = self.window_start(stamp.utc);
+        if self.windows.back().is_none_or(|w| w.start < start) {
+            self.windows.push_back(Window {
+                start,
+                lines: 0,
+                max: Maximals::new(self.count),
+            });
+        }
+        // windows without lines take no place, the oldest kept is `keep - 1` widths before,
+        // all of them are when that is out of range
+        let oldest = i32::try_from(self.keep.saturating_sub(1))
+            .ok()
+            .and_then(|k| self.width.checked_mul(k))
+            .and_then(|span| start.checked_sub_signed(span));
+        if let Some(oldest) = oldest {
+            while self.windows.front().is_some_and(|w| w.start < oldest) {
+                self.windows.pop_front();
+            }
+        }
+
+        // lines out of order belong to the last window
+        if let Some(window) = self.windows.back_mut() {
+            window.lines += 1;
+            window.max.insert(WindowEntry {
+                last: stamp.last,
+                total: stamp.total,
+                line,
+                text: Rc::from(text.trim_end()),
+            });
+        }
+    }
+}
+
+impl fmt::Display for Windows {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for w in &self.windows {
+            writeln!(
+                f,
+                "{} lines {}",
+                w.start.format("%Y-%m-%d %H:%M:%S%.3f"),
+                w.lines
+            )?;
+            for e in w.max.iter() {
+                writeln!(
+                    f,
+                    "  Δ{:.4} @{:.4} line {}: {}",
+                    e.last.as_secs_f32(),
+                    e.total.as_secs_f32(),
+                    e.line,
+                    e.text
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::windows::Windows;
+    use chrono::DateTime;
+    use std::time::Duration;
+    use txt_timer::timer::Stamp;
+
+    fn stamp(last: u64, total: u64) -> Stamp {
+        Stamp {
+            last: Duration::from_secs(last),
+            total: Duration::from_secs(total),
+            utc: DateTime::from_timestamp(1670833140 + total as i64, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn rolling_windows() {
+        let mut w = Windows::new(Duration::from_secs(60), 2, 1);
+        w.insert(&stamp(0, 0), 1, "a\n");
+        w.insert(&stamp(50, 50), 2, "b\n");
+        w.insert(&stamp(10, 60), 3, "c\n");
+        w.insert(&stamp(5, 65), 4, "d\n");
+        w.insert(&stamp(20, 85), 5, "e\n");
+        assert_eq!(
+            w.to_string(),
+            "2022-12-12 08:19:00.000 lines 2\n  Δ50.0000 @50.0000 line 2: b\n\
+             2022-12-12 08:20:00.000 lines 3\n  Δ20.0000 @85.0000 line 5: e\n"
+        );
+
+        // the window of 08:21 is empty, only the one of 08:22 is kept
+        w.insert(&stamp(95, 180), 6, "f\n");
+        assert_eq!(
+            w.to_string(),
+            "2022-12-12 08:22:00.000 lines 1\n  Δ95.0000 @180.0000 line 6: f\n"
+        );
+    }
+
+    #[test]
+    fn wide_windows() {
+        let mut w = Windows::new(Duration::from_secs(10_000_000_000_000), 6, 1);
+        w.insert(&stamp(0, 0), 1, "a\n");
+        w.insert(&stamp(5, 5), 2, "b\n");
+        assert!(w
+            .to_string()
+            .ends_with("lines 2\n  Δ5.0000 @5.0000 line 2: b\n"));
+    }
+}
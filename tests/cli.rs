@@ -1,3 +1,4 @@
+use assert_cmd::cargo_bin;
 use assert_cmd::prelude::*;
 use assert_fs::prelude::PathChild;
 use predicates::prelude::*;
@@ -7,7 +8,7 @@ use std::process::{Command, Stdio};
 
 #[test]
 fn parse_time() -> Result<(), Box<dyn std::error::Error>> {
-    let mut child = Command::cargo_bin("txt-timer")?
+    let mut child = Command::new(cargo_bin!("txt-timer"))
         .arg("--time-regex")
         .arg("(?P<time>[0-9: -]*\\.\\d{3})")
         .arg("--time-regex-format")
@@ -37,7 +38,7 @@ fn parse_time() -> Result<(), Box<dyn std::error::Error>> {
 
 #[test]
 fn parse_time_iso() -> Result<(), Box<dyn std::error::Error>> {
-    let mut child = Command::cargo_bin("txt-timer")?
+    let mut child = Command::new(cargo_bin!("txt-timer"))
         .arg("--time-regex-iso")
         .arg("-B")
         .arg("1")
@@ -64,7 +65,7 @@ fn parse_time_iso() -> Result<(), Box<dyn std::error::Error>> {
 
 #[test]
 fn parse_time_lines_before() -> Result<(), Box<dyn std::error::Error>> {
-    let mut child = Command::cargo_bin("txt-timer")?
+    let mut child = Command::new(cargo_bin!("txt-timer"))
         .arg("--time-regex")
         .arg("(?P<time>[0-9: -]*\\.\\d{3})")
         .arg("--time-regex-format")
@@ -92,6 +93,103 @@ fn parse_time_lines_before() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn parse_time_lines_after() -> Result<(), Box<dyn std::error::Error>> {
+    let mut child = Command::new(cargo_bin!("txt-timer"))
+        .arg("--time-regex")
+        .arg("(?P<time>[0-9: -]*\\.\\d{3})")
+        .arg("--time-regex-format")
+        .arg("%Y-%m-%d %H:%M:%S%.3f")
+        .arg("-C")
+        .arg("1")
+        .arg("-c")
+        .arg("2")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn child process");
+
+    let mut stdin = child.stdin.take().expect("Failed to open stdin");
+    std::thread::spawn(move || {
+        stdin
+            .write_all(
+                "2022-12-12 08:19:00.000 a\n2022-12-12 08:19:01.000 b\n2022-12-12 08:19:01.001 c\n2022-12-12 08:19:03.001 d\n"
+                    .as_bytes(),
+            )
+            .expect("Failed to write to stdin");
+    });
+
+    let output = child.wait_with_output().expect("Failed to read stdout");
+    assert_eq!(String::from_utf8_lossy(&output.stdout),
+               "2022-12-12 08:19:00.000 a\n2022-12-12 08:19:01.000 b\n2022-12-12 08:19:01.001 c\n2022-12-12 08:19:03.001 d\n\nMaximals:\nΔ2.0000 @3.0010\n2022-12-12 08:19:01.001 c\n2022-12-12 08:19:03.001 d\n\n\nΔ1.0000 @1.0000\n2022-12-12 08:19:00.000 a\n2022-12-12 08:19:01.000 b\n2022-12-12 08:19:01.001 c\n\n\n\n");
+    Ok(())
+}
+
+#[test]
+fn parse_time_merge_contexts() -> Result<(), Box<dyn std::error::Error>> {
+    let mut child = Command::new(cargo_bin!("txt-timer"))
+        .arg("--time-regex")
+        .arg("(?P<time>[0-9: -]*\\.\\d{3})")
+        .arg("--time-regex-format")
+        .arg("%Y-%m-%d %H:%M:%S%.3f")
+        .arg("-B")
+        .arg("1")
+        .arg("-c")
+        .arg("3")
+        .arg("--merge-contexts")
+        .arg("-q")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn child process");
+
+    let mut stdin = child.stdin.take().expect("Failed to open stdin");
+    std::thread::spawn(move || {
+        stdin
+            .write_all(
+                "2022-12-12 08:19:00.000 a\n2022-12-12 08:19:01.000 b\n2022-12-12 08:19:01.001 c\n2022-12-12 08:19:01.002 d\n2022-12-12 08:19:01.003 e\n2022-12-12 08:19:02.003 f\n"
+                    .as_bytes(),
+            )
+            .expect("Failed to write to stdin");
+    });
+
+    let output = child.wait_with_output().expect("Failed to read stdout");
+    assert_eq!(String::from_utf8_lossy(&output.stdout),
+               "\nMaximals:\n#1 Δ1.0000 @2.0030\n#2 Δ1.0000 @1.0000\n#3 Δ0.0010 @1.0030\n\n2022-12-12 08:19:00.000 a\n#2 Δ1.0000 @1.0000\n2022-12-12 08:19:01.000 b\n\n\n2022-12-12 08:19:01.002 d\n#3 Δ0.0010 @1.0030\n2022-12-12 08:19:01.003 e\n#1 Δ1.0000 @2.0030\n2022-12-12 08:19:02.003 f\n\n\n\n");
+    Ok(())
+}
+
+#[test]
+fn parse_time_line_number() -> Result<(), Box<dyn std::error::Error>> {
+    let mut child = Command::new(cargo_bin!("txt-timer"))
+        .arg("--time-regex")
+        .arg("(?P<time>[0-9: -]*\\.\\d{3})")
+        .arg("--time-regex-format")
+        .arg("%Y-%m-%d %H:%M:%S%.3f")
+        .arg("-B")
+        .arg("1")
+        .arg("-c")
+        .arg("1")
+        .arg("-n")
+        .arg("-p")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn child process");
+
+    let mut stdin = child.stdin.take().expect("Failed to open stdin");
+    std::thread::spawn(move || {
+        stdin
+            .write_all("2022-12-12 08:19:00.000 a\nx\n2022-12-12 08:19:01.000 b\n".as_bytes())
+            .expect("Failed to write to stdin");
+    });
+
+    let output = child.wait_with_output().expect("Failed to read stdout");
+    assert_eq!(String::from_utf8_lossy(&output.stdout),
+               "Δ0.0000 @0.0000 2022-12-12T08:19:00+00:00 line 1, byte 0\n2022-12-12 08:19:00.000 a\nx\nΔ1.0000 @1.0000 2022-12-12T08:19:01+00:00 line 3, byte 28\n2022-12-12 08:19:01.000 b\n\nMaximals:\nΔ1.0000 @1.0000 line 3, byte 28\n1-2022-12-12 08:19:00.000 a\n3:2022-12-12 08:19:01.000 b\n\n\n\n");
+    Ok(())
+}
+
 #[test]
 fn parse_time_write_to_file() -> Result<(), Box<dyn std::error::Error>> {
     let temp_dir = assert_fs::TempDir::new()?;
@@ -100,7 +198,7 @@ fn parse_time_write_to_file() -> Result<(), Box<dyn std::error::Error>> {
     let maximals_file_str = maximals_file_path
         .to_str()
         .expect("cannot convert string for tmp file");
-    let mut child = Command::cargo_bin("txt-timer")?
+    let mut child = Command::new(cargo_bin!("txt-timer"))
         .arg("--time-regex")
         .arg("(?P<time>[0-9: -]*\\.\\d{3})")
         .arg("--time-regex-format")
@@ -137,7 +235,7 @@ fn parse_time_write_to_file() -> Result<(), Box<dyn std::error::Error>> {
 
 #[test]
 fn bad_regex() -> Result<(), Box<dyn std::error::Error>> {
-    Command::cargo_bin("txt-timer")?
+    Command::new(cargo_bin!("txt-timer"))
         .arg("--time-regex")
         .arg("(?P<ime>[0-9: -]*\\.\\d{3})")
         .arg("--time-regex-format")
@@ -153,7 +251,7 @@ fn bad_regex() -> Result<(), Box<dyn std::error::Error>> {
 
 #[test]
 fn bad_regex_combination() -> Result<(), Box<dyn std::error::Error>> {
-    Command::cargo_bin("txt-timer")?
+    Command::new(cargo_bin!("txt-timer"))
         .arg("--time-regex-iso")
         .arg("--time-regex")
         .arg("(?P<time>[0-9: -]*\\.\\d{3})")
@@ -167,3 +265,565 @@ fn bad_regex_combination() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn bad_seconds() -> Result<(), Box<dyn std::error::Error>> {
+    for args in [
+        ["--summary-interval", "nan"].as_slice(),
+        &["--summary-interval", "1e30"],
+        &["--window", "inf"],
+        &["--window", "nan"],
+        &["--bursts", "--burst-interval", "1e30"],
+        &["--bursts", "--burst-gap", "nan"],
+        &["--stats", "--color-range", "inf"],
+        &["--color-range", "0"],
+    ] {
+        Command::new(cargo_bin!("txt-timer"))
+            .args(args)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(
+                "must be a positive number of seconds",
+            ));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn stats_summary() -> Result<(), Box<dyn std::error::Error>> {
+    assert_cmd::Command::new(cargo_bin!("txt-timer"))
+        .arg("--time-regex-iso")
+        .arg("--stats")
+        .arg("-q")
+        .write_stdin("2022-12-12T08:19:00.000Z a\n2022-12-12T08:19:01.000Z b\n2022-12-12T08:19:01.001Z c\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Statistics:\nlines 3 total 1.0010\nmean 0.3337 median 0.0010 p90 1.0000 p99 1.0000 max 1.0000\n",
+        ))
+        .stdout(predicate::str::contains(
+            "over 0.2000: 1.0000 in 1 gaps (99.9% of total)\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn attribute_before() -> Result<(), Box<dyn std::error::Error>> {
+    assert_cmd::Command::new(cargo_bin!("txt-timer"))
+        .arg("--time-regex-iso")
+        .arg("--attribute")
+        .arg("before")
+        .arg("-p")
+        .arg("-B")
+        .arg("0")
+        .arg("-A")
+        .arg("1")
+        .arg("-c")
+        .arg("1")
+        .write_stdin("2022-12-12T08:19:00.000Z a\n2022-12-12T08:19:01.000Z b\n2022-12-12T08:19:01.001Z c\n")
+        .assert()
+        .success()
+        .stdout("2022-12-12T08:19:00.000Z a\nΔ1.0000 @0.0000 2022-12-12T08:19:00+00:00\n2022-12-12T08:19:01.000Z b\nΔ0.0010 @1.0000 2022-12-12T08:19:01+00:00\n2022-12-12T08:19:01.001Z c\nΔ0.0000 @1.0010 2022-12-12T08:19:01.001+00:00\n\nMaximals:\nΔ1.0000 @0.0000\n2022-12-12T08:19:00.000Z a\n2022-12-12T08:19:01.000Z b\n\n\n\n");
+
+    Ok(())
+}
+
+#[test]
+fn filter_lines() -> Result<(), Box<dyn std::error::Error>> {
+    let input = "2022-12-12T08:19:00.000Z a\n2022-12-12T08:19:01.000Z heartbeat\n2022-12-12T08:19:02.001Z c\n";
+    let expected = format!(
+        "{input}\nMaximals:\nΔ2.0010 @2.0010\n2022-12-12T08:19:00.000Z a\n2022-12-12T08:19:02.001Z c\n\n\n\n"
+    );
+
+    assert_cmd::Command::new(cargo_bin!("txt-timer"))
+        .arg("--time-regex-iso")
+        .arg("--ignore")
+        .arg("heartbeat")
+        .arg("-c")
+        .arg("1")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected.clone());
+
+    assert_cmd::Command::new(cargo_bin!("txt-timer"))
+        .arg("--time-regex-iso")
+        .arg("--only")
+        .arg(" a$")
+        .arg("--only")
+        .arg(" c$")
+        .arg("-c")
+        .arg("1")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+
+    Ok(())
+}
+
+#[test]
+fn bad_pair_regex() -> Result<(), Box<dyn std::error::Error>> {
+    Command::new(cargo_bin!("txt-timer"))
+        .arg("--pair-start")
+        .arg("req (?P<id>[0-9]+)")
+        .arg("--pair-end")
+        .arg("resp ([0-9]+)")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "pair regexes must have a `(?P<id>exp)` capturing group",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn record_start() -> Result<(), Box<dyn std::error::Error>> {
+    assert_cmd::Command::new(cargo_bin!("txt-timer"))
+        .arg("--time-regex-iso")
+        .arg("--record-start")
+        .arg("^[0-9]{4}-")
+        .arg("-B")
+        .arg("0")
+        .arg("-c")
+        .arg("1")
+        .arg("-q")
+        .write_stdin("2022-12-12T08:19:00.000Z a\n2022-12-12T08:19:01.000Z exception\n  at foo\n  at bar\n2022-12-12T08:19:01.001Z c\n")
+        .assert()
+        .success()
+        .stdout("\nMaximals:\nΔ1.0000 @1.0000\n2022-12-12T08:19:01.000Z exception\n  at foo\n  at bar\n\n\n\n");
+
+    Ok(())
+}
+
+#[test]
+fn replay_recording() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let cast = dir.child("session.cast");
+    std::fs::write(
+        cast.path(),
+        "{\"version\": 2, \"width\": 80, \"height\": 24, \"timestamp\": 1670833140}\n\
+         [0.0, \"o\", \"a\\n\"]\n\
+         [1.5, \"o\", \"b\\nc\"]\n\
+         [1.75, \"o\", \"\\n\"]\n",
+    )?;
+    let record = dir.child("again.cast");
+
+    Command::new(cargo_bin!("txt-timer"))
+        .arg("-B")
+        .arg("0")
+        .arg("-c")
+        .arg("1")
+        .arg("-q")
+        .arg("--record")
+        .arg(record.path())
+        .arg("replay")
+        .arg(cast.path())
+        .assert()
+        .success()
+        .stdout("\nMaximals:\nΔ1.5000 @1.5000\nb\n\n\n\n");
+
+    assert_eq!(
+        read_to_string(record.path())?,
+        "{\"height\":24,\"timestamp\":1670833140,\"title\":\"txt-timer\",\"version\":2,\"width\":80}\n\
+         [0.0,\"o\",\"a\\r\\n\"]\n\
+         [1.5,\"o\",\"b\\r\\n\"]\n\
+         [1.75,\"o\",\"c\\r\\n\"]\n"
+    );
+
+    // replaying the recording gives back the lines as they were read
+    Command::new(cargo_bin!("txt-timer"))
+        .arg("-c")
+        .arg("0")
+        .arg("replay")
+        .arg(record.path())
+        .assert()
+        .success()
+        .stdout("a\nb\nc\n\nMaximals:\n\n");
+
+    for speed in ["nan", "inf", "0"] {
+        Command::new(cargo_bin!("txt-timer"))
+            .arg("replay")
+            .arg("--speed")
+            .arg(speed)
+            .arg(cast.path())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(
+                "replay speed must be a positive number",
+            ));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn diff_logs() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let old = dir.child("old.log");
+    std::fs::write(
+        old.path(),
+        "2022-12-12T08:19:00.000Z start\n2022-12-12T08:19:01.000Z compile 1\n2022-12-12T08:19:03.000Z fetch\n",
+    )?;
+    let new = dir.child("new.log");
+    std::fs::write(
+        new.path(),
+        "2022-12-12T08:19:00.000Z start\n2022-12-12T08:19:04.000Z compile 2\n2022-12-12T08:19:04.500Z test\n",
+    )?;
+
+    Command::new(cargo_bin!("txt-timer"))
+        .arg("--time-regex")
+        .arg("^(?P<time>[0-9:T-]*\\.[0-9]{3})Z ")
+        .arg("--time-regex-format")
+        .arg("%Y-%m-%dT%H:%M:%S%.3f")
+        .arg("diff")
+        .arg(old.path())
+        .arg(new.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "total 3.0000 -> 4.5000 (+1.5000)\n\
+             changed most:\n      \
+                   1.0000       4.0000      +3.0000 <num>-<num>-<num>T<num>:<num>:<num>Z compile <num>\n\
+             only in old:\n      \
+                   2.0000        1 <num>-<num>-<num>T<num>:<num>:<num>Z fetch\n\
+             only in new:\n      \
+                   0.5000        1 <num>-<num>-<num>T<num>:<num>:<num>Z test\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn baseline_regression() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let baseline = dir.child("baseline.jsonl");
+
+    assert_cmd::Command::new(cargo_bin!("txt-timer"))
+        .arg("--time-regex-iso")
+        .arg("-q")
+        .arg("--save-baseline")
+        .arg(baseline.path())
+        .write_stdin("2022-12-12T08:19:00.000Z start\n2022-12-12T08:19:01.000Z build\n2022-12-12T08:19:02.000Z test\n")
+        .assert()
+        .success();
+
+    assert_cmd::Command::new(cargo_bin!("txt-timer"))
+        .arg("--time-regex-iso")
+        .arg("-q")
+        .arg("--baseline")
+        .arg(baseline.path())
+        .arg("--fail-on-regression")
+        .write_stdin("2022-12-12T08:19:00.000Z start\n2022-12-12T08:19:03.000Z build\n2022-12-12T08:19:04.000Z test\n")
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "Regressions:\nover +20%: 1 of 3 templates\n      \
+                   1.0000       3.0000   +200.0% <num>-<num>-<num>T<num>:<num>:<num>Z build\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn summary_interval() -> Result<(), Box<dyn std::error::Error>> {
+    let mut child = Command::new(cargo_bin!("txt-timer"))
+        .arg("--time-regex-iso")
+        .arg("-q")
+        .arg("-B")
+        .arg("0")
+        .arg("--summary-interval")
+        .arg("0.1")
+        .arg("--summary-reset")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn child process");
+
+    let mut stdin = child.stdin.take().expect("Failed to open stdin");
+    stdin
+        .write_all(b"2022-12-12T08:19:00.000Z a\n2022-12-12T08:19:01.000Z b\n")
+        .expect("Failed to write to stdin");
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    stdin
+        .write_all(b"2022-12-12T08:19:03.000Z c\n")
+        .expect("Failed to write to stdin");
+    drop(stdin);
+
+    let output = child.wait_with_output().expect("Failed to read stdout");
+    let summaries = String::from_utf8_lossy(&output.stderr);
+    assert!(summaries.starts_with("\nMaximals:\nΔ1.0000 @1.0000\n2022-12-12T08:19:01.000Z b\n"));
+    // reset after the first interval, so the last delay is the only one at the end
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "\nMaximals:\nΔ2.0000 @3.0000\n2022-12-12T08:19:03.000Z c\n\n\n\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn windows() -> Result<(), Box<dyn std::error::Error>> {
+    assert_cmd::Command::new(cargo_bin!("txt-timer"))
+        .arg("--time-regex-iso")
+        .arg("-q")
+        .arg("-c")
+        .arg("1")
+        .arg("-B")
+        .arg("0")
+        .arg("--window")
+        .arg("60")
+        .arg("--windows")
+        .arg("2")
+        .write_stdin("2022-12-12T08:18:00.000Z a\n2022-12-12T08:19:00.000Z b\n2022-12-12T08:19:10.000Z c\n2022-12-12T08:20:05.000Z d\n2022-12-12T08:20:06.000Z e\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Windows:\n\
+             2022-12-12 08:19:00.000 lines 2\n  Δ60.0000 @60.0000 line 2: 2022-12-12T08:19:00.000Z b\n\
+             2022-12-12 08:20:00.000 lines 2\n  Δ55.0000 @125.0000 line 4: 2022-12-12T08:20:05.000Z d\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn bursts() -> Result<(), Box<dyn std::error::Error>> {
+    assert_cmd::Command::new(cargo_bin!("txt-timer"))
+        .arg("--time-regex-iso")
+        .arg("-q")
+        .arg("-c")
+        .arg("1")
+        .arg("--bursts")
+        .write_stdin("2022-12-12T08:19:00.000Z a\n2022-12-12T08:19:00.500Z b\n2022-12-12T08:19:00.500Z c\n2022-12-12T08:19:00.500Z d\n2022-12-12T08:19:02.000Z e\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::ends_with(
+            "Bursts:\n\
+             most lines per 1s:\n       \
+                    4 lines 2022-12-12 08:19:00.000 (4.0/s)\n\
+             longest runs of delays under 0.001s:\n       \
+                    3 lines in 0.0000 @0.5000 line 2: 2022-12-12T08:19:00.500Z b\n\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn anomaly() -> Result<(), Box<dyn std::error::Error>> {
+    assert_cmd::Command::new(cargo_bin!("txt-timer"))
+        .arg("--time-regex-iso")
+        .arg("-q")
+        .arg("-c")
+        .arg("1")
+        .arg("-B")
+        .arg("0")
+        .arg("--anomaly")
+        .arg("--anomaly-alpha")
+        .arg("0.5")
+        .write_stdin("2022-12-12T08:19:00.000Z a\n2022-12-12T08:19:01.000Z b\n2022-12-12T08:19:02.000Z c\n2022-12-12T08:19:03.000Z d\n2022-12-12T08:19:03.010Z e\n2022-12-12T08:19:03.020Z f\n2022-12-12T08:19:03.030Z g\n2022-12-12T08:19:03.040Z h\n2022-12-12T08:19:03.340Z slow\n2022-12-12T08:19:03.350Z i\n")
+        .assert()
+        .success()
+        .stdout("\nMaximals:\nΔ0.3000 @3.3400 z1.05\n2022-12-12T08:19:03.340Z slow\n\n\n\n");
+
+    Ok(())
+}
+
+#[test]
+fn color_line() -> Result<(), Box<dyn std::error::Error>> {
+    assert_cmd::Command::new(cargo_bin!("txt-timer"))
+        .env("CLICOLOR_FORCE", "1")
+        .arg("--time-regex-iso")
+        .arg("--color-line")
+        .arg("--color-threshold")
+        .arg("0=green")
+        .arg("--color-threshold")
+        .arg("1=red")
+        .write_stdin("2022-12-12T08:19:00.000Z a\n2022-12-12T08:19:01.000Z b\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "\x1b[42m2022-12-12T08:19:00.000Z a\x1b[0m\n\x1b[41m2022-12-12T08:19:01.000Z b\x1b[0m\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn inline_stamps() -> Result<(), Box<dyn std::error::Error>> {
+    assert_cmd::Command::new(cargo_bin!("txt-timer"))
+        .arg("--time-regex-iso")
+        .arg("--stamp-layout")
+        .arg("inline")
+        .arg("--stamp-fields")
+        .arg("line,delta,wall")
+        .arg("--wall-format")
+        .arg("%H:%M:%S%.3f")
+        .arg("-c")
+        .arg("0")
+        .write_stdin("2022-12-12T08:19:00.000Z a\nno time\n2022-12-12T08:19:01.500Z b\n")
+        .assert()
+        .success()
+        .stdout(format!(
+            "      1 Δ   0.0000 08:19:00.000 2022-12-12T08:19:00.000Z a\n{:32}no time\n      \
+             3 Δ   1.5000 08:19:01.500 2022-12-12T08:19:01.500Z b\n\nMaximals:\n\n",
+            ""
+        ));
+
+    assert_cmd::Command::new(cargo_bin!("txt-timer"))
+        .env("TZ", "UTC")
+        .arg("--time-regex-iso")
+        .arg("--stamp-layout")
+        .arg("ts")
+        .arg("-c")
+        .arg("0")
+        .write_stdin("2022-12-12T08:19:00.000Z a\n")
+        .assert()
+        .success()
+        .stdout("Dec 12 08:19:00 2022-12-12T08:19:00.000Z a\n\nMaximals:\n\n");
+
+    Ok(())
+}
+
+#[test]
+fn color_choice() -> Result<(), Box<dyn std::error::Error>> {
+    let run = |choice: &str, force: &str| {
+        assert_cmd::Command::new(cargo_bin!("txt-timer"))
+            .env("CLICOLOR_FORCE", force)
+            .env_remove("NO_COLOR")
+            .arg("--color")
+            .arg(choice)
+            .arg("-c")
+            .arg("0")
+            .write_stdin("a\n")
+            .assert()
+            .success()
+    };
+    run("always", "0").stdout("a\n\n\x1b[1;33mMaximals\x1b[0m:\n\n");
+    run("never", "1").stdout("a\n\nMaximals:\n\n");
+    run("auto", "0").stdout("a\n\nMaximals:\n\n");
+    run("auto", "1").stdout("a\n\n\x1b[1;33mMaximals\x1b[0m:\n\n");
+
+    Ok(())
+}
+
+#[test]
+fn annotate_to() -> Result<(), Box<dyn std::error::Error>> {
+    let input = "2022-01-01T10:00:00.000Z a\r\n2022-01-01T10:00:01.500Z b\nno end";
+    let run = |target: &str| {
+        assert_cmd::Command::new(cargo_bin!("txt-timer"))
+            .env_remove("CLICOLOR_FORCE")
+            .arg("--time-regex-iso")
+            .arg("-p")
+            .arg("--annotate-to")
+            .arg(target)
+            .arg("-c")
+            .arg("1")
+            .write_stdin(input)
+            .assert()
+            .success()
+    };
+
+    let output = run("stderr").stdout(input).get_output().clone();
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("Δ1.5000"));
+    assert!(stderr.contains("Maximals:\nΔ1.5000"));
+
+    let temp = assert_fs::TempDir::new()?;
+    let log = temp.child("timing.log");
+    run(log.path().to_str().unwrap()).stdout(input).stderr("");
+    let annotated = read_to_string(log.path())?;
+    assert!(annotated
+        .contains("Δ1.5000 @1.5000 2022-01-01T10:00:01.500+00:00\n2022-01-01T10:00:01.500Z b\n"));
+    assert!(annotated.contains("Maximals:\nΔ1.5000"));
+
+    Ok(())
+}
+
+#[test]
+fn annotate_to_raw_bytes() -> Result<(), Box<dyn std::error::Error>> {
+    let input: &[u8] = b"2022-01-01T10:00:00.000Z a\xff\n2022-01-01T10:00:01.500Z \xfe b\n";
+    let output = assert_cmd::Command::new(cargo_bin!("txt-timer"))
+        .arg("--time-regex-iso")
+        .arg("-p")
+        .arg("--annotate-to")
+        .arg("stderr")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(input)
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Δ1.5000 @1.5000 2022-01-01T10:00:01.500+00:00\n"));
+    assert!(stderr.contains("Maximals:\nΔ1.5000"));
+
+    Ok(())
+}
+
+#[test]
+fn line_number_raw_bytes() -> Result<(), Box<dyn std::error::Error>> {
+    let input: &[u8] = b"2022-01-01T10:00:00.000Z a\xff\n2022-01-01T10:00:01.500Z b\n";
+    let output = assert_cmd::Command::new(cargo_bin!("txt-timer"))
+        .arg("--time-regex-iso")
+        .arg("-n")
+        .arg("-p")
+        .arg("-c")
+        .arg("1")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Δ1.5000 @1.5000 2022-01-01T10:00:01.500+00:00 line 2, byte 28\n"));
+    assert!(stdout.contains("Maximals:\nΔ1.5000 @1.5000 line 2, byte 28\n"));
+
+    Ok(())
+}
+
+#[test]
+fn no_terminal() -> Result<(), Box<dyn std::error::Error>> {
+    assert_cmd::Command::new(cargo_bin!("txt-timer"))
+        .arg("--browse")
+        .arg("-c")
+        .arg("0")
+        .write_stdin("a\n")
+        .assert()
+        .success()
+        .stdout("a\n\nMaximals:\n\n")
+        .stderr(predicate::str::contains("--browse needs a terminal"));
+
+    assert_cmd::Command::new(cargo_bin!("txt-timer"))
+        .arg("--tui")
+        .write_stdin("a\n")
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(predicate::str::contains("--tui needs a terminal"));
+
+    assert_cmd::Command::new(cargo_bin!("txt-timer"))
+        .arg("--tui")
+        .arg("--summary-interval")
+        .arg("1")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--summary-interval does not support --tui",
+        ));
+
+    assert_cmd::Command::new(cargo_bin!("txt-timer"))
+        .arg("--annotate-to")
+        .arg("stderr")
+        .arg("--browse")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "does not support --tui or --browse",
+        ));
+
+    Ok(())
+}